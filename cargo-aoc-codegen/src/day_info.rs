@@ -0,0 +1,703 @@
+//! Finding the entry points in a day's source file.
+
+use anyhow::{bail, ensure, format_err, Context, Result};
+use std::{fs, path::Path};
+use syn::{Attribute, FnArg, Item, ItemFn, Lit, Meta, NestedMeta, ReturnType, Type, Visibility};
+
+/// How a day's input is parsed, worked out from the functions and types in its source file.
+///
+/// `part1` and `part2` take a reference to whatever is parsed: a slice for `Lines` and
+/// `Paragraphs`, or the parsed value itself for `Whole` and `FromStr`.
+///
+/// If a day has more than one, the first in the order below is used, so a parsing function always
+/// wins over `FromStr`. A function marked with `#[aoc::parse(year, day)]` is called with the whole
+/// input, and takes priority over the rest.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ParseStyle {
+    /// `pub fn parse_input(input: &str) -> Result<T>` is called with the whole input.
+    Whole,
+    /// `pub fn parse_paragraph(paragraph: &str) -> Result<T>` is called for each group of lines
+    /// separated by a blank line.
+    Paragraphs,
+    /// `pub fn parse(line: &str) -> Result<T>` is called for each line.
+    Lines,
+    /// The whole input is parsed into `pub struct Input` (or `enum`, or `type`) with `FromStr`.
+    ///
+    /// Only used if the source file has an `impl FromStr for Input`, as `Input` is a common name
+    /// for what `parse` returns.
+    FromStr,
+}
+
+impl ParseStyle {
+    /// The function that does the parsing, if there is one.
+    fn fn_name(self) -> Option<&'static str> {
+        match self {
+            ParseStyle::Lines => Some("parse"),
+            ParseStyle::Whole => Some("parse_input"),
+            ParseStyle::Paragraphs => Some("parse_paragraph"),
+            ParseStyle::FromStr => None,
+        }
+    }
+}
+
+/// The entry points of a day.
+#[derive(Debug)]
+pub struct DayInfo {
+    pub parse: ParseStyle,
+    /// The function that does the parsing, `None` for `FromStr`.
+    pub parse_fn: Option<String>,
+    /// The solutions, ordered by part.
+    pub solutions: Vec<Solution>,
+    /// Functions that are named like solutions but were left out, and why.
+    pub warnings: Vec<String>,
+}
+
+/// A function that solves one part of a day.
+#[derive(Debug)]
+pub struct Solution {
+    pub part: u8,
+    pub fn_name: String,
+    /// Tells apart several solutions for the same part. Given with
+    /// `#[aoc::part(year, day, part, name = "...")]`, or taken from a function name such as
+    /// `part1_simd`.
+    pub name: Option<String>,
+}
+
+/// What an `#[aoc::...]` attribute registers a function as.
+enum Registration {
+    Parse,
+    Part { part: u8, name: Option<String> },
+}
+
+impl DayInfo {
+    /// Read a day's source file and find its entry points.
+    ///
+    /// Fails with an explanation if the entry points are missing or have signatures that the
+    /// generated code can't call.
+    pub fn load(path: impl AsRef<Path>, year: u16, day: u8) -> Result<Self> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)?;
+        Self::analyse(&source, year, day).with_context(|| format!("in {}", path.display()))
+    }
+
+    fn analyse(source: &str, year: u16, day: u8) -> Result<Self> {
+        let file = syn::parse_file(source).map_err(|e| {
+            let start = e.span().start();
+            format_err!(
+                "cannot parse source file at line {}, column {}: {}",
+                start.line,
+                start.column + 1,
+                e
+            )
+        })?;
+
+        let mut parse: Option<ParseStyle> = None;
+        let mut attr_parse: Option<String> = None;
+        let mut solutions: Vec<Solution> = vec![];
+        let mut warnings: Vec<String> = vec![];
+        let mut pub_input = false;
+        let mut input_from_str = false;
+        for item in &file.items {
+            match item {
+                Item::Fn(item) => {
+                    let name = item.sig.ident.to_string();
+                    let (style, part, part_name) = match registration(&item.attrs, year, day)? {
+                        Some(Registration::Parse) => {
+                            if let Some(other) = &attr_parse {
+                                bail!("`{}` and `{}` are both marked `#[aoc::parse]`", other, name);
+                            }
+                            attr_parse = Some(name.clone());
+                            (Some(ParseStyle::Whole), None, None)
+                        }
+                        Some(Registration::Part { part, name }) => (None, Some(part), name),
+                        None => {
+                            let style = [ParseStyle::Whole, ParseStyle::Paragraphs, ParseStyle::Lines]
+                                .into_iter()
+                                .find(|style| style.fn_name() == Some(&name));
+                            let (part, part_name) = match part_from_name(&name, &item.vis) {
+                                // Only the name says it's a variant, so a helper named like one is
+                                // left out rather than failing the build.
+                                Some((part, Some(variant))) => match check_signature(item, false) {
+                                    Ok(()) => (Some(part), Some(variant)),
+                                    Err(e) => {
+                                        warnings.push(format!(
+                                            "`{}` is not used as a solution for part {}: {} \
+                                            (register it with `#[aoc::part]` to use it)",
+                                            name, part, e
+                                        ));
+                                        (None, None)
+                                    }
+                                },
+                                Some((part, None)) => (Some(part), None),
+                                None => (None, None),
+                            };
+                            if let Some(style) = style {
+                                parse = Some(parse.map_or(style, |other| other.min(style)));
+                            }
+                            (style, part, part_name)
+                        }
+                    };
+                    if style.is_none() && part.is_none() {
+                        continue;
+                    }
+                    check_signature(item, style.is_some())?;
+                    if let Some(part) = part {
+                        if let Some(other) = solutions
+                            .iter()
+                            .find(|other| other.part == part && other.name == part_name)
+                        {
+                            bail!(
+                                "`{}` and `{}` are both solutions for part {}{}, \
+                                give them different names",
+                                other.fn_name,
+                                name,
+                                part,
+                                match &part_name {
+                                    Some(part_name) => format!(" named \"{}\"", part_name),
+                                    None => String::new(),
+                                }
+                            );
+                        }
+                        solutions.push(Solution {
+                            part,
+                            fn_name: name,
+                            name: part_name,
+                        });
+                    }
+                }
+                Item::Struct(syn::ItemStruct { ident, vis, .. })
+                | Item::Enum(syn::ItemEnum { ident, vis, .. })
+                | Item::Type(syn::ItemType { ident, vis, .. })
+                    if ident == "Input" && is_pub(vis) =>
+                {
+                    pub_input = true;
+                }
+                Item::Impl(item) if is_from_str_for_input(item) => input_from_str = true,
+                _ => (),
+            }
+        }
+
+        if pub_input && input_from_str {
+            let style = ParseStyle::FromStr;
+            parse = Some(parse.map_or(style, |other| other.min(style)));
+        }
+
+        let (parse, parse_fn) = match (attr_parse, parse) {
+            (Some(parse_fn), _) => (ParseStyle::Whole, Some(parse_fn)),
+            (None, Some(style)) => (style, style.fn_name().map(String::from)),
+            (None, None) => bail!(
+                "no way to parse the input: add `pub fn parse(line: &str)`, \
+                `pub fn parse_input(input: &str)`, `pub fn parse_paragraph(paragraph: &str)`, \
+                a `pub struct Input` that implements `FromStr`, \
+                or mark a function with `#[aoc::parse({}, {})]`",
+                year,
+                day
+            ),
+        };
+        ensure!(
+            !solutions.is_empty(),
+            "no solutions: add `pub fn part1` or `pub fn part2`, \
+            or mark functions with `#[aoc::part({}, {}, 1)]`",
+            year,
+            day
+        );
+        // Stable, so solutions for the same part stay in source order.
+        solutions.sort_by_key(|solution| solution.part);
+        Ok(DayInfo {
+            parse,
+            parse_fn,
+            solutions,
+            warnings,
+        })
+    }
+}
+
+/// The part solved by a function named `part1` or `part2`, or by a variant such as `part1_simd`.
+///
+/// Only `pub` functions are variants, so private helpers like `fn part1_step` are left alone. Public
+/// ones whose signatures don't fit are left out too, with a warning.
+fn part_from_name(name: &str, vis: &Visibility) -> Option<(u8, Option<String>)> {
+    let (part, variant) = match name.split_once('_') {
+        Some((part, variant)) => (part, Some(variant)),
+        None => (name, None),
+    };
+    let part = match part {
+        "part1" => 1,
+        "part2" => 2,
+        _ => return None,
+    };
+    match variant {
+        None => Some((part, None)),
+        Some(variant) if !variant.is_empty() && is_pub(vis) => Some((part, Some(variant.into()))),
+        Some(_) => None,
+    }
+}
+
+/// Check that a parser (if `parser` is set) or a solution can be called by the generated code.
+fn check_signature(item: &ItemFn, parser: bool) -> Result<()> {
+    let name = &item.sig.ident;
+    ensure!(is_pub(&item.vis), "`{}` must be `pub`", name);
+    ensure!(
+        item.sig.generics.params.is_empty(),
+        "`{}` must not have generic parameters",
+        name
+    );
+    let arg = match single_arg(&item.sig.inputs) {
+        Some(arg) => arg,
+        None => bail!(
+            "`{}` must take exactly one argument, found {}",
+            name,
+            item.sig.inputs.len()
+        ),
+    };
+    ensure!(
+        !matches!(item.sig.output, ReturnType::Default),
+        "`{}` must return something",
+        name
+    );
+    if parser {
+        ensure!(is_str_ref(arg), "`{}` must take a `&str`", name);
+        ensure!(
+            returns_result(&item.sig.output),
+            "`{}` must return a `Result`",
+            name
+        );
+    } else {
+        ensure!(
+            matches!(arg, Type::Reference(r) if r.mutability.is_none()),
+            "`{}` must take a shared reference to the parsed input, e.g. `&[T]`",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Find an `#[aoc::parse(year, day)]` or `#[aoc::part(year, day, part)]` attribute.
+///
+/// The attribute's year and day must be the ones of the file it is in.
+fn registration(attrs: &[Attribute], year: u16, day: u8) -> Result<Option<Registration>> {
+    for attr in attrs {
+        let segments = attr
+            .path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<_>>();
+        let kind = match segments.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+            ["aoc", kind @ ("parse" | "part")] => kind,
+            _ => continue,
+        };
+        let args = match attr.parse_meta()? {
+            Meta::List(list) => list.nested.into_iter().collect::<Vec<_>>(),
+            _ => bail!("`#[aoc::{}]` needs arguments", kind),
+        };
+        let mut numbers = vec![];
+        let mut name = None;
+        for arg in &args {
+            match arg {
+                NestedMeta::Lit(Lit::Int(lit)) => numbers.push(lit.base10_parse::<u16>()?),
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("name") => match &nv.lit {
+                    Lit::Str(lit) => name = Some(lit.value()),
+                    _ => bail!("the name in `#[aoc::{}]` must be a string", kind),
+                },
+                _ => bail!("unexpected argument to `#[aoc::{}]`", kind),
+            }
+        }
+        let expected = if kind == "parse" { 2 } else { 3 };
+        ensure!(
+            numbers.len() == expected,
+            "`#[aoc::{}]` takes {}, found {} numbers",
+            kind,
+            if kind == "parse" { "a year and a day" } else { "a year, a day and a part" },
+            numbers.len()
+        );
+        ensure!(
+            numbers[0] == year && numbers[1] == u16::from(day),
+            "`#[aoc::{}({}, {}, ..)]` is in the file for year {}, day {}",
+            kind,
+            numbers[0],
+            numbers[1],
+            year,
+            day
+        );
+        return Ok(Some(match kind {
+            "parse" => Registration::Parse,
+            _ => {
+                ensure!(
+                    numbers[2] == 1 || numbers[2] == 2,
+                    "part must be 1 or 2, found {}",
+                    numbers[2]
+                );
+                Registration::Part {
+                    part: numbers[2] as u8,
+                    name,
+                }
+            }
+        }));
+    }
+    Ok(None)
+}
+
+fn is_pub(vis: &Visibility) -> bool {
+    matches!(vis, Visibility::Public(_))
+}
+
+/// Whether this is `impl FromStr for Input`, or `impl std::str::FromStr for Input`.
+fn is_from_str_for_input(item: &syn::ItemImpl) -> bool {
+    let is_from_str = match &item.trait_ {
+        Some((None, path, _)) => path.segments.last().unwrap().ident == "FromStr",
+        _ => false,
+    };
+    is_from_str && matches!(&*item.self_ty, Type::Path(ty) if ty.path.is_ident("Input"))
+}
+
+fn single_arg(inputs: &syn::punctuated::Punctuated<FnArg, syn::Token![,]>) -> Option<&Type> {
+    match (inputs.len(), inputs.first()) {
+        (1, Some(FnArg::Typed(arg))) => Some(&arg.ty),
+        _ => None,
+    }
+}
+
+fn is_str_ref(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(r) => matches!(&*r.elem, Type::Path(p) if p.path.is_ident("str")),
+        _ => false,
+    }
+}
+
+fn returns_result(output: &ReturnType) -> bool {
+    match output {
+        ReturnType::Type(_, ty) => match &**ty {
+            Type::Path(p) => p
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "Result"),
+            _ => false,
+        },
+        ReturnType::Default => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyse(source: &str) -> Result<DayInfo> {
+        DayInfo::analyse(source, 2021, 1)
+    }
+
+    fn error(source: &str) -> String {
+        format!("{:#}", analyse(source).unwrap_err())
+    }
+
+    fn solutions(info: &DayInfo) -> Vec<(u8, &str, Option<&str>)> {
+        info.solutions
+            .iter()
+            .map(|solution| {
+                (
+                    solution.part,
+                    solution.fn_name.as_str(),
+                    solution.name.as_deref(),
+                )
+            })
+            .collect()
+    }
+
+    const PARTS: &str = "
+        pub fn part1(input: &[i64]) -> i64 { 0 }
+        pub fn part2(input: &[i64]) -> i64 { 0 }
+    ";
+
+    #[test]
+    fn parse_styles() {
+        let cases = [
+            (
+                "pub fn parse(line: &str) -> Result<i64> { todo!() }",
+                ParseStyle::Lines,
+            ),
+            (
+                "pub fn parse_input(input: &str) -> Result<i64> { todo!() }",
+                ParseStyle::Whole,
+            ),
+            (
+                "pub fn parse_paragraph(paragraph: &str) -> Result<i64> { todo!() }",
+                ParseStyle::Paragraphs,
+            ),
+            (
+                "pub struct Input;\nimpl FromStr for Input {}",
+                ParseStyle::FromStr,
+            ),
+            (
+                "pub enum Input {}\nimpl std::str::FromStr for Input {}",
+                ParseStyle::FromStr,
+            ),
+            (
+                "pub type Input = Grid;\nimpl FromStr for Input {}",
+                ParseStyle::FromStr,
+            ),
+        ];
+        for (parser, style) in cases {
+            let info = analyse(&format!("{}\n{}", parser, PARTS)).unwrap();
+            assert_eq!(info.parse, style, "{}", parser);
+            assert_eq!(info.parse_fn.as_deref(), style.fn_name(), "{}", parser);
+        }
+    }
+
+    #[test]
+    fn parse_style_priority() {
+        let source = format!(
+            "
+            pub fn parse(line: &str) -> Result<i64> {{ todo!() }}
+            pub struct Input;
+            impl FromStr for Input {{}}
+            pub fn parse_paragraph(paragraph: &str) -> Result<i64> {{ todo!() }}
+            {}
+            ",
+            PARTS
+        );
+        assert_eq!(analyse(&source).unwrap().parse, ParseStyle::Paragraphs);
+
+        let whole = format!(
+            "{}\npub fn parse_input(input: &str) -> Result<i64> {{ todo!() }}",
+            source
+        );
+        assert_eq!(analyse(&whole).unwrap().parse, ParseStyle::Whole);
+
+        // The attribute wins over every function found by name.
+        let attribute = format!(
+            "{}\n#[aoc::parse(2021, 1)]\npub fn read(input: &str) -> Result<i64> {{ todo!() }}",
+            whole
+        );
+        let info = analyse(&attribute).unwrap();
+        assert_eq!(info.parse, ParseStyle::Whole);
+        assert_eq!(info.parse_fn.as_deref(), Some("read"));
+    }
+
+    #[test]
+    fn private_input_is_not_a_parser() {
+        let source = format!("struct Input;\nimpl FromStr for Input {{}}\n{}", PARTS);
+        assert!(error(&source).contains("no way to parse the input"));
+    }
+
+    #[test]
+    fn input_parsed_by_lines() {
+        // `Input` is what `parse` returns, not something parsed with `FromStr`.
+        let info = analyse(
+            "
+            pub struct Input(i64);
+            pub fn parse(line: &str) -> Result<Input> { todo!() }
+            pub fn part1(input: &[Input]) -> i64 { 0 }
+            ",
+        )
+        .unwrap();
+        assert_eq!(info.parse, ParseStyle::Lines);
+        assert_eq!(info.parse_fn.as_deref(), Some("parse"));
+
+        let source = format!("pub struct Input;\n{}", PARTS);
+        assert!(error(&source).contains("no way to parse the input"));
+    }
+
+    #[test]
+    fn missing_part2() {
+        let info = analyse(
+            "
+            pub fn parse(line: &str) -> Result<i64> { todo!() }
+            pub fn part1(input: &[i64]) -> i64 { 0 }
+            ",
+        )
+        .unwrap();
+        assert_eq!(solutions(&info), [(1, "part1", None)]);
+    }
+
+    #[test]
+    fn no_solutions() {
+        let source = "pub fn parse(line: &str) -> Result<i64> { todo!() }";
+        assert!(error(source).contains("no solutions"));
+    }
+
+    #[test]
+    fn variants() {
+        let info = analyse(
+            r#"
+            pub fn parse(line: &str) -> Result<i64> { todo!() }
+            pub fn part2(input: &[i64]) -> i64 { 0 }
+            pub fn part1_simd(input: &[i64]) -> i64 { 0 }
+            pub fn part1(input: &[i64]) -> i64 { 0 }
+            #[aoc::part(2021, 1, 1, name = "fast")]
+            pub fn quick(input: &[i64]) -> i64 { 0 }
+            fn part1_step(input: &[i64]) -> i64 { 0 }
+            "#,
+        )
+        .unwrap();
+        // Ordered by part, then as they are in the source.
+        assert_eq!(
+            solutions(&info),
+            [
+                (1, "part1_simd", Some("simd")),
+                (1, "part1", None),
+                (1, "quick", Some("fast")),
+                (2, "part2", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn helper_named_like_a_variant() {
+        let info = analyse(
+            "
+            pub fn parse(line: &str) -> Result<i64> { todo!() }
+            pub fn part1(input: &[i64]) -> i64 { 0 }
+            pub fn part1_step(input: &[i64], at: usize) -> i64 { 0 }
+            ",
+        )
+        .unwrap();
+        assert_eq!(solutions(&info), [(1, "part1", None)]);
+        assert_eq!(
+            info.warnings,
+            [
+                "`part1_step` is not used as a solution for part 1: `part1_step` must take exactly \
+                one argument, found 2 (register it with `#[aoc::part]` to use it)"
+            ]
+        );
+
+        // Without the name to go on, a bad signature is still an error.
+        let source = "
+            pub fn parse(line: &str) -> Result<i64> { todo!() }
+            pub fn part1(input: &[i64], at: usize) -> i64 { 0 }
+        ";
+        assert!(error(source).contains("`part1` must take exactly one argument"));
+    }
+
+    #[test]
+    fn duplicate_variants() {
+        let source = r#"
+            pub fn parse(line: &str) -> Result<i64> { todo!() }
+            pub fn part1_simd(input: &[i64]) -> i64 { 0 }
+            #[aoc::part(2021, 1, 1, name = "simd")]
+            pub fn other(input: &[i64]) -> i64 { 0 }
+        "#;
+        assert!(error(source)
+            .contains("`part1_simd` and `other` are both solutions for part 1 named \"simd\""));
+
+        let source = "
+            pub fn parse(line: &str) -> Result<i64> { todo!() }
+            pub fn part1(input: &[i64]) -> i64 { 0 }
+            #[aoc::part(2021, 1, 1)]
+            pub fn other(input: &[i64]) -> i64 { 0 }
+        ";
+        assert!(error(source).contains("`part1` and `other` are both solutions for part 1,"));
+
+        let source = format!(
+            "
+            #[aoc::parse(2021, 1)]
+            pub fn read(input: &str) -> Result<i64> {{ todo!() }}
+            #[aoc::parse(2021, 1)]
+            pub fn load(input: &str) -> Result<i64> {{ todo!() }}
+            {}
+            ",
+            PARTS
+        );
+        assert!(error(&source).contains("`read` and `load` are both marked `#[aoc::parse]`"));
+    }
+
+    #[test]
+    fn attribute_for_another_day() {
+        let source = format!(
+            "
+            #[aoc::parse(2021, 2)]
+            pub fn read(input: &str) -> Result<i64> {{ todo!() }}
+            {}
+            ",
+            PARTS
+        );
+        assert!(error(&source).contains("is in the file for year 2021, day 1"));
+
+        let source = "
+            pub fn parse(line: &str) -> Result<i64> { todo!() }
+            #[aoc::part(2020, 1, 1)]
+            pub fn first(input: &[i64]) -> i64 { 0 }
+        ";
+        assert!(error(source).contains("`#[aoc::part(2020, 1, ..)]` is in the file for year 2021"));
+    }
+
+    #[test]
+    fn bad_attributes() {
+        let cases = [
+            (
+                "#[aoc::part(2021, 1)]",
+                "takes a year, a day and a part, found 2 numbers",
+            ),
+            ("#[aoc::part(2021, 1, 3)]", "part must be 1 or 2, found 3"),
+            ("#[aoc::part(2021, 1, 1, name = 2)]", "must be a string"),
+            ("#[aoc::part]", "needs arguments"),
+        ];
+        for (attribute, message) in cases {
+            let source = format!(
+                "
+                pub fn parse(line: &str) -> Result<i64> {{ todo!() }}
+                {}
+                pub fn first(input: &[i64]) -> i64 {{ 0 }}
+                ",
+                attribute
+            );
+            let error = error(&source);
+            assert!(error.contains(message), "{}: {}", attribute, error);
+        }
+    }
+
+    #[test]
+    fn bad_signatures() {
+        let cases = [
+            (
+                "fn parse(line: &str) -> Result<i64> { todo!() }",
+                "`parse` must be `pub`",
+            ),
+            (
+                "pub fn parse<T>(line: &str) -> Result<i64> { todo!() }",
+                "`parse` must not have generic parameters",
+            ),
+            (
+                "pub fn parse(line: &str, extra: u8) -> Result<i64> { todo!() }",
+                "`parse` must take exactly one argument, found 2",
+            ),
+            (
+                "pub fn parse(line: &str) {}",
+                "`parse` must return something",
+            ),
+            (
+                "pub fn parse(line: String) -> Result<i64> { todo!() }",
+                "must take a `&str`",
+            ),
+            (
+                "pub fn parse(line: &str) -> i64 { 0 }",
+                "must return a `Result`",
+            ),
+            (
+                "pub fn part1(input: Vec<i64>) -> i64 { 0 }",
+                "`part1` must take a shared reference to the parsed input",
+            ),
+            (
+                "pub fn part1(input: &mut [i64]) -> i64 { 0 }",
+                "`part1` must take a shared reference to the parsed input",
+            ),
+        ];
+        for (item, message) in cases {
+            let source = if item.contains("parse") {
+                format!("{}\n{}", item, PARTS)
+            } else {
+                format!(
+                    "pub fn parse(line: &str) -> Result<i64> {{ todo!() }}\n{}",
+                    item
+                )
+            };
+            let error = error(&source);
+            assert!(error.contains(message), "{}: {}", item, error);
+        }
+    }
+
+    #[test]
+    fn syntax_error() {
+        assert!(error("pub fn parse(").contains("cannot parse source file at line 1"));
+    }
+}
@@ -0,0 +1,1021 @@
+//! Generating the code that runs the solutions in a `cargo-aoc` project.
+//!
+//! `cargo-aoc` uses this to write the generated files into a project. Projects created with
+//! `cargo aoc new --build-script` call [`build`] from their build script instead, so the code is
+//! generated into `OUT_DIR` on every build, and picks up new days without running `cargo-aoc`.
+
+mod day_info;
+
+pub use day_info::{DayInfo, ParseStyle, Solution};
+
+use anyhow::{bail, ensure, Context, Error, Result};
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    env,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// The name of the generated project, and of its main binary.
+pub const PROJECT_NAME: &str = "aoc";
+/// The config file in the project root.
+pub const CONFIG_PATH: &str = ".aoc.toml";
+/// The crate that provides `#[aoc::parse]` and `#[aoc::part]`.
+pub const MACROS_CRATE: &str = "cargo-aoc-macros";
+/// This crate, which projects using a build script depend on.
+pub const CODEGEN_CRATE: &str = "cargo-aoc-codegen";
+/// Where generated projects get our crates from, as they aren't published.
+pub const REPOSITORY: &str = "https://github.com/derekdreery/cargo-aoc";
+/// The version of [`MACROS_CRATE`] generated projects depend on.
+///
+/// A proc macro crate can't export it, so it is kept in step with `cargo-aoc-macros/Cargo.toml`
+/// by hand.
+pub const MACROS_VERSION: &str = "0.1.0";
+/// The version of this crate, which generated projects depend on.
+pub const CODEGEN_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// How the generated code gets hold of puzzle input.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputMode {
+    /// Embed the input in the binary with `include_str!`.
+    #[default]
+    Embed,
+    /// Read the input from the project folder when the solution is run.
+    Runtime,
+}
+
+impl FromStr for InputMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "embed" => Ok(InputMode::Embed),
+            "runtime" => Ok(InputMode::Runtime),
+            other => bail!(
+                "unknown input mode {:?} (expected `embed` or `runtime`)",
+                other
+            ),
+        }
+    }
+}
+
+/// How the generated code is laid out.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// All solutions are compiled into a single binary, `src/main.rs`.
+    #[default]
+    Modules,
+    /// Each day also gets its own binary in `src/bin`, and the runner lives in a library crate.
+    Bins,
+}
+
+impl FromStr for Layout {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "modules" => Ok(Layout::Modules),
+            "bins" => Ok(Layout::Bins),
+            other => bail!("unknown layout {:?} (expected `modules` or `bins`)", other),
+        }
+    }
+}
+
+/// The parts of the project's config that affect the generated code.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Settings {
+    #[serde(skip)]
+    pub project_root: PathBuf,
+    #[serde(default)]
+    pub input_mode: InputMode,
+    #[serde(default)]
+    pub layout: Layout,
+    /// Whether the code is generated by the project's build script, which passes warnings on to
+    /// cargo rather than logging them.
+    #[serde(skip)]
+    pub in_build_script: bool,
+}
+
+impl Settings {
+    /// Read the settings from the config file of the project at `project_root`.
+    pub fn load(project_root: impl Into<PathBuf>) -> Result<Self> {
+        let project_root = project_root.into();
+        let path = project_root.join(CONFIG_PATH);
+        let bytes = fs::read(&path).with_context(|| format!("cannot read {}", path.display()))?;
+        let mut settings: Settings =
+            toml::from_slice(&bytes).with_context(|| format!("cannot parse {}", path.display()))?;
+        settings.project_root = project_root;
+        Ok(settings)
+    }
+
+    /// Show a warning about the code being generated.
+    pub fn warn(&self, message: &str) {
+        if self.in_build_script {
+            println!("cargo:warning={}", message);
+        } else {
+            log::warn!("{}", message);
+        }
+    }
+
+    /// The folder for the source files for the given year.
+    pub fn year_folder(&self, year: u16) -> PathBuf {
+        self.project_root.join(format!("src/_{}", year))
+    }
+
+    /// The source file for the given year and day.
+    pub fn day_source(&self, year: u16, day: u8) -> PathBuf {
+        self.project_root
+            .join(format!("src/_{}/day{}.rs", year, day))
+    }
+}
+
+/// The input file for the given year and day, relative to the project root.
+///
+/// Always uses `/` as the separator, so it is the same on every machine.
+pub fn input_rel_path(year: u16, day: u8) -> String {
+    format!("input/{}/input{}.txt", year, day)
+}
+
+/// The cargo feature that compiles in the solutions for the given year.
+pub fn year_feature(year: u16) -> String {
+    format!("y{}", year)
+}
+
+/// Get all the years that have a folder in `src`.
+///
+/// Only for [`build`], `cargo-aoc` finds the years when it scans the project.
+fn years(settings: &Settings) -> Result<BTreeSet<u16>> {
+    let mut years = BTreeSet::new();
+    let src = settings.project_root.join("src");
+    for entry in fs::read_dir(&src).with_context(|| format!("cannot read {}", src.display()))? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            years.extend(parse_year(&entry.file_name(), "_"));
+        }
+    }
+    Ok(years)
+}
+
+/// Get all the days in the given year that have a source file.
+fn days_present(settings: &Settings, year: u16) -> Result<BTreeSet<u8>> {
+    let mut days_present = BTreeSet::new();
+    let folder = settings.year_folder(year);
+    let entries =
+        fs::read_dir(&folder).with_context(|| format!("cannot read {}", folder.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            days_present.extend(parse_day(&entry.file_name(), "day", ".rs"));
+        }
+    }
+    Ok(days_present)
+}
+
+/// The year in a file name that is exactly `{prefix}{year}`, e.g. `_2021` in `src`.
+///
+/// Anything else, like `old_2021`, `_02021` or a name that isn't UTF-8, isn't one of ours.
+pub fn parse_year(name: &OsStr, prefix: &str) -> Option<u16> {
+    parse_exact(name, prefix, "").filter(|year| *year >= 2015)
+}
+
+/// The day in a file name that is exactly `{prefix}{day}{suffix}`, e.g. `day1.rs`.
+///
+/// Anything else, like `oldday1.rs`, `day01.rs`, `day300.rs` or a name that isn't UTF-8, isn't one
+/// of ours.
+pub fn parse_day(name: &OsStr, prefix: &str, suffix: &str) -> Option<u8> {
+    parse_exact(name, prefix, suffix).filter(|day| (1..=25).contains(day))
+}
+
+/// Parse the number between `prefix` and `suffix`, if it is written the way we would write it.
+fn parse_exact<T: FromStr + ToString>(name: &OsStr, prefix: &str, suffix: &str) -> Option<T> {
+    let number = name.to_str()?.strip_prefix(prefix)?.strip_suffix(suffix)?;
+    let value = number.parse::<T>().ok()?;
+    (value.to_string() == number).then_some(value)
+}
+
+/// Whether the source of a day registers its solutions with `#[aoc::parse]` or `#[aoc::part]`.
+///
+/// The generated project only depends on the attributes' crate once they are used.
+pub fn source_uses_attributes(source: &str) -> bool {
+    Regex::new(r"#\s*\[\s*aoc\s*::\s*(parse|part)\b")
+        .unwrap()
+        .is_match(source)
+}
+
+/// Whether any day registers its solutions with the attributes, for [`build`].
+fn uses_attributes(settings: &Settings) -> Result<bool> {
+    for year in years(settings)? {
+        for day in days_present(settings, year)? {
+            let path = settings.day_source(year, day);
+            let source = fs::read_to_string(&path)
+                .with_context(|| format!("cannot read {}", path.display()))?;
+            if source_uses_attributes(&source) {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Generate the code that runs the solutions into `$OUT_DIR/aoc.rs`.
+///
+/// Call this from the project's build script, and include the result in `src/main.rs` with
+/// `include!(concat!(env!("OUT_DIR"), "/aoc.rs"));`. Only the modules layout is supported.
+pub fn build() -> Result<()> {
+    let project_root = env::var_os("CARGO_MANIFEST_DIR").context("not run by cargo")?;
+    let out_dir = env::var_os("OUT_DIR").context("not run by cargo")?;
+    let mut settings = Settings::load(project_root)?;
+    settings.in_build_script = true;
+    ensure!(
+        settings.layout == Layout::Modules,
+        "generating code from a build script only works with the modules layout"
+    );
+    // Any new or changed day file can change the generated code, and so can input being
+    // downloaded for a day that had none.
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=input");
+    println!("cargo:rerun-if-changed={}", CONFIG_PATH);
+    println!("cargo:rerun-if-changed=Cargo.toml");
+
+    let features = declared_features(&settings.project_root)?;
+    let years = years(&settings)?.into_iter().collect::<Vec<_>>();
+    let gates = years
+        .iter()
+        .map(|year| {
+            let feature = year_feature(*year);
+            if features.contains(&feature) {
+                quote!(#[cfg(feature = #feature)])
+            } else {
+                // A year added by hand has no feature until `cargo-aoc` adds one, so always
+                // compile it in rather than never.
+                settings.warn(&format!(
+                    "there is no `{}` feature, so year {} is always compiled",
+                    feature, year
+                ));
+                quote!()
+            }
+        })
+        .collect::<Vec<_>>();
+    let year_idents = years
+        .iter()
+        .map(|year| format_ident!("_{}", year))
+        .collect::<Vec<_>>();
+    let year_items = years
+        .iter()
+        .map(|year| year_items(&settings, *year, &days_present(&settings, *year)?, true))
+        .collect::<Result<Vec<_>>>()?;
+    let macros = if uses_attributes(&settings)? {
+        let macros = macros_ident();
+        quote!(extern crate #macros as aoc;)
+    } else {
+        quote!()
+    };
+    let runner = runner_items(quote!());
+    let main = main_fn(&settings, &years, &gates, quote!());
+
+    let code = quote!(
+        use qu::ick_use::*;
+
+        #macros
+
+        #(
+            #gates
+            mod #year_idents {
+                #year_items
+            }
+        )*
+
+        #runner
+
+        #main
+    );
+    let path = Path::new(&out_dir).join("aoc.rs");
+    fs::write(&path, format(code)?).with_context(|| format!("cannot write {}", path.display()))?;
+    Ok(())
+}
+
+/// Format generated code, so it is readable, e.g. when the compiler points into it.
+pub fn format(code: TokenStream) -> Result<String> {
+    let file = syn::parse2::<syn::File>(code).context("generated code is not valid Rust")?;
+    Ok(separate_items(&prettyplease::unparse(&file)))
+}
+
+/// Put a blank line between top-level items, which the pretty printer leaves out. Runs of `use`s
+/// and of `mod` declarations stay together.
+fn separate_items(code: &str) -> String {
+    let is_group = |line: &str, keyword: &str| {
+        let line = line.strip_prefix("pub ").unwrap_or(line);
+        line.starts_with(keyword) && line.ends_with(';')
+    };
+    let mut out = String::with_capacity(code.len());
+    let mut prev = "";
+    for line in code.lines() {
+        let top_level = !line.is_empty() && !line.starts_with([' ', '{', '}', ')', ']']);
+        let after_item = !prev.is_empty()
+            && !prev.starts_with(' ')
+            && (prev.ends_with(';') || prev.ends_with('}'));
+        let separate = top_level
+            && after_item
+            && !(is_group(prev, "use ") && is_group(line, "use "))
+            && !(is_group(prev, "mod ") && (is_group(line, "mod ") || line.starts_with("#[")));
+        if separate {
+            out.push('\n');
+        }
+        out.push_str(line);
+        out.push('\n');
+        prev = line;
+    }
+    out
+}
+
+/// The features declared in the project's `Cargo.toml`.
+fn declared_features(project_root: &Path) -> Result<BTreeSet<String>> {
+    let path = project_root.join("Cargo.toml");
+    let manifest: toml::Value = toml::from_slice(
+        &fs::read(&path).with_context(|| format!("cannot read {}", path.display()))?,
+    )?;
+    Ok(manifest
+        .get("features")
+        .and_then(|features| features.as_table())
+        .map(|features| features.keys().cloned().collect())
+        .unwrap_or_default())
+}
+
+/// The `main` function, which runs the selected years, and fails if any of their parts failed.
+///
+/// `gates` are the attributes that compile each year in or out, and `prelude` goes at the start of
+/// the function.
+pub fn main_fn(
+    settings: &Settings,
+    years: &[u16],
+    gates: &[TokenStream],
+    prelude: TokenStream,
+) -> TokenStream {
+    let year_idents = years
+        .iter()
+        .map(|year| format_ident!("_{}", year))
+        .collect::<Vec<_>>();
+    let runner = runner_path(settings);
+    quote!(
+        #[qu::ick]
+        fn main(opt: Opt) {
+            #prelude
+            if let Some(part) = opt.part {
+                ensure!(part == 1 || part == 2, "part must be 1 or 2, found {}", part);
+            }
+            if opt.input.is_some() {
+                ensure!(
+                    opt.year.is_some() && opt.day.is_some(),
+                    "`--input` requires `--year` and `--day`"
+                );
+            }
+            match opt.year {
+                Some(year) => {
+                    #(
+                        #gates
+                        if #years == year {
+                            crate::#year_idents::run(&opt)?;
+                            return #runner::finish();
+                        }
+                    )*
+                    bail!("year {} is not available (is the `y{}` feature enabled?)", year, year)
+                },
+                None => {
+                    log::info!("Running all available solutions");
+                    #(
+                        #gates
+                        crate::#year_idents::run(&opt)?;
+                    )*
+                    #runner::finish()
+                }
+            }
+        }
+    )
+}
+
+/// The contents of a year's module: a module for each of `days_present`, `run` to run the selected
+/// days, and the functions that run each day.
+///
+/// With `absolute_paths`, the day modules point at their source files with `#[path]`, for when the
+/// year's module is not in the year's folder.
+pub fn year_items(
+    settings: &Settings,
+    year: u16,
+    days_present: &BTreeSet<u8>,
+    absolute_paths: bool,
+) -> Result<TokenStream> {
+    let days = days_present.iter().collect::<Vec<_>>();
+    let days_mods = days_present
+        .iter()
+        .map(|day| {
+            let day_mod = format_ident!("day{}", day);
+            if absolute_paths {
+                let path = settings.day_source(year, *day);
+                let path = path.to_str().context("non-utf8 path")?;
+                Ok(quote!(#[path = #path] mod #day_mod;))
+            } else {
+                Ok(quote!(mod #day_mod;))
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let days_fns = days_present
+        .iter()
+        .map(|day| format_ident!("run_day{}", day))
+        .collect::<Vec<_>>();
+    let days_runners = days_present
+        .iter()
+        .map(|day| day_runner(settings, year, *day))
+        .collect::<Result<Vec<_>>>()?;
+    let runner = runner_path(settings);
+
+    Ok(quote!(
+        use qu::ick_use::*;
+        use std::time::Instant;
+
+        #(#days_mods)*
+
+        pub fn run(opt: &#runner::Opt) -> Result {
+            #(
+                if opt.day.map_or(true, |day| day == #days) {
+                    #days_fns(opt)?;
+                }
+            )*
+            Ok(())
+        }
+
+        #(#days_runners)*
+    ))
+}
+
+/// The items shared by all the generated runners: command line options, and reporting results.
+///
+/// `opt_fields` are added to the end of the command line options.
+pub fn runner_items(opt_fields: TokenStream) -> TokenStream {
+    quote!(
+        use std::{
+            borrow::Cow,
+            fmt, fs,
+            io::{self, Read},
+            path::{Path, PathBuf},
+            str::FromStr,
+            sync::atomic::{AtomicBool, Ordering},
+            time::{Duration, Instant},
+        };
+
+        #[derive(StructOpt)]
+        pub struct Opt {
+            /// Specify the year you want to run.
+            ///
+            /// Defaults to all years
+            #[structopt(long, short)]
+            pub year: Option<u16>,
+            /// Specify the day you want to run.
+            ///
+            /// Defaults to all the days.
+            #[structopt(long, short)]
+            pub day: Option<u8>,
+            /// Specify the part you want to run, either `1` or `2`.
+            ///
+            /// Defaults to both parts.
+            #[structopt(long, short)]
+            pub part: Option<u8>,
+            /// Run against this file instead of the downloaded input. Use `-` to read from stdin.
+            ///
+            /// Requires `--year` and `--day`.
+            #[structopt(long, short, parse(from_os_str))]
+            pub input: Option<PathBuf>,
+            /// How to print results, either `text` or `json`.
+            ///
+            /// `json` prints one record per line to stdout for each part that is run. See `Record`
+            /// for the schema.
+            #[structopt(long, short, default_value = "text")]
+            pub format: Format,
+            #opt_fields
+        }
+
+        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+        pub enum Format {
+            Text,
+            Json,
+        }
+
+        impl FromStr for Format {
+            type Err = Error;
+
+            fn from_str(s: &str) -> Result<Self> {
+                match s {
+                    "text" => Ok(Format::Text),
+                    "json" => Ok(Format::Json),
+                    other => bail!("unknown format {:?} (expected `text` or `json`)", other),
+                }
+            }
+        }
+
+        /// The result of running one part of one day.
+        ///
+        /// With `--format json`, every record is printed as a single line of JSON with these
+        /// fields. Fields may be added in the future, but existing fields will not be removed,
+        /// renamed or change type.
+        ///
+        ///  - `year` (number)
+        ///  - `day` (number)
+        ///  - `part` (number): `1` or `2`
+        ///  - `variant` (string or null): the name of the solution, for parts with more than one,
+        ///    e.g. `"simd"` for `part1_simd`
+        ///  - `status` (string): `"ok"` if the part ran, `"error"` if it could not be run,
+        ///    `"no_input"` if the input for the day hasn't been downloaded yet, or `"mismatch"` if
+        ///    the part ran but its answer differs from an earlier variant of the same part
+        ///  - `answer` (string or null): the answer, formatted with `Display`. `null` unless
+        ///    `status` is `"ok"` or `"mismatch"`
+        ///  - `error` (string or null): what went wrong. `null` unless `status` is `"error"` or
+        ///    `"mismatch"`
+        ///  - `parse_ns` (number): time taken to parse the input, in nanoseconds
+        ///  - `part_ns` (number or null): time taken to run the part, in nanoseconds. `null`
+        ///    unless `status` is `"ok"` or `"mismatch"`
+        pub struct Record {
+            pub year: u16,
+            pub day: u8,
+            pub part: u8,
+            pub variant: Option<&'static str>,
+            pub status: Status,
+            pub parse_time: Duration,
+            pub part_time: Option<Duration>,
+        }
+
+        pub enum Status {
+            Ok(String),
+            Error(String),
+            NoInput,
+            /// The answer differs from the one given by an earlier variant of the part.
+            Mismatch {
+                answer: String,
+                expected: String,
+                expected_variant: Option<&'static str>,
+            },
+        }
+
+        impl Record {
+            pub fn report(&self, format: Format) {
+                match format {
+                    Format::Text => {
+                        let part = part_label(self.part, self.variant);
+                        match &self.status {
+                            Status::Ok(answer) => log::info!(
+                                "  Part {} result: {} (parse: {:?}, run: {:?})",
+                                part,
+                                answer,
+                                self.parse_time,
+                                self.part_time.unwrap_or_default()
+                            ),
+                            Status::Error(error) => log::error!("  Part {} failed: {}", part, error),
+                            Status::NoInput => log::warn!("  Part {} skipped: no input", part),
+                            Status::Mismatch { answer, .. } => log::error!(
+                                "  Part {} result: {} (parse: {:?}, run: {:?}) {}",
+                                part,
+                                answer,
+                                self.parse_time,
+                                self.part_time.unwrap_or_default(),
+                                self.status.mismatch(self.part).unwrap()
+                            ),
+                        }
+                    }
+                    Format::Json => {
+                        let (status, answer, error) = match &self.status {
+                            Status::Ok(answer) => ("ok", Some(answer), None),
+                            Status::Error(error) => ("error", None, Some(error.clone())),
+                            Status::NoInput => ("no_input", None, None),
+                            Status::Mismatch { answer, .. } => {
+                                ("mismatch", Some(answer), self.status.mismatch(self.part))
+                            }
+                        };
+                        let record = serde_json::json!({
+                            "year": self.year,
+                            "day": self.day,
+                            "part": self.part,
+                            "variant": self.variant,
+                            "status": status,
+                            "answer": answer,
+                            "error": error,
+                            "parse_ns": self.parse_time.as_nanos() as u64,
+                            "part_ns": self.part_time.map(|time| time.as_nanos() as u64),
+                        });
+                        println!("{}", record);
+                    }
+                }
+            }
+        }
+
+        impl Status {
+            /// Describe a mismatch between variants.
+            fn mismatch(&self, part: u8) -> Option<String> {
+                match self {
+                    Status::Mismatch {
+                        expected,
+                        expected_variant,
+                        ..
+                    } => Some(format!(
+                        "does not match part {}, which gave {}",
+                        part_label(part, *expected_variant),
+                        expected
+                    )),
+                    _ => None,
+                }
+            }
+        }
+
+        /// A part number, with the variant's name if it has one.
+        fn part_label(part: u8, variant: Option<&str>) -> String {
+            match variant {
+                Some(variant) => format!("{} ({})", part, variant),
+                None => part.to_string(),
+            }
+        }
+
+        /// Where to find the downloaded input for a day.
+        ///
+        /// Only one variant is used, depending on the input mode in `.aoc.toml`.
+        #[allow(dead_code)]
+        pub enum Input {
+            /// The input was embedded in the binary.
+            Embedded(&'static str),
+            /// The input is read from this path at runtime.
+            File(&'static str),
+        }
+
+        /// Get the input for a day, from `--input` if it was given.
+        ///
+        /// Returns `None` if the input hasn't been downloaded, which is marked by an empty or
+        /// missing input file.
+        pub fn input(opt: &Opt, downloaded: Input) -> Result<Option<Cow<'static, str>>> {
+            let path = match (&opt.input, downloaded) {
+                (Some(path), _) => path,
+                (None, Input::Embedded("")) => return Ok(None),
+                (None, Input::Embedded(input)) => return Ok(Some(Cow::Borrowed(input))),
+                (None, Input::File(path)) => {
+                    return match fs::read_to_string(path) {
+                        Ok(input) if input.is_empty() => Ok(None),
+                        Ok(input) => Ok(Some(Cow::Owned(input))),
+                        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                        Err(e) => {
+                            Err(Error::new(e).context(format!("cannot read input from {}", path)))
+                        }
+                    }
+                }
+            };
+            let mut input = String::new();
+            if path == Path::new("-") {
+                io::stdin()
+                    .read_to_string(&mut input)
+                    .context("cannot read input from stdin")?;
+            } else {
+                input = fs::read_to_string(path)
+                    .with_context(|| format!("cannot read input from {}", path.display()))?;
+            }
+            Ok(Some(Cow::Owned(input)))
+        }
+
+        /// Whether any part has failed so far, i.e. its input could not be parsed or it gave
+        /// different answers.
+        static FAILED: AtomicBool = AtomicBool::new(false);
+
+        /// Fail the run if any part failed, once everything selected has run.
+        pub fn finish() -> Result {
+            ensure!(!FAILED.load(Ordering::Relaxed), "some parts failed, see above");
+            Ok(())
+        }
+
+        /// Whether the given part was selected with `--part`.
+        pub fn part_selected(opt: &Opt, part: u8) -> bool {
+            opt.part.map_or(true, |selected| selected == part)
+        }
+
+        /// Runs the solutions for one part of a day, and checks that they give the same answer.
+        pub struct Part<'a> {
+            opt: &'a Opt,
+            year: u16,
+            day: u8,
+            part: u8,
+            parse_time: Duration,
+            /// The first answer, and the variant that gave it.
+            first: Option<(Option<&'static str>, String)>,
+        }
+
+        impl<'a> Part<'a> {
+            pub fn new(opt: &'a Opt, year: u16, day: u8, part: u8, parse_time: Duration) -> Self {
+                Part {
+                    opt,
+                    year,
+                    day,
+                    part,
+                    parse_time,
+                    first: None,
+                }
+            }
+
+            /// Run one solution, and report the answer.
+            ///
+            /// If an earlier solution for the part gave a different answer, the answer is reported
+            /// as a mismatch, which fails the run.
+            pub fn run<T: fmt::Display>(&mut self, variant: Option<&'static str>, f: impl FnOnce() -> T) {
+                if !part_selected(self.opt, self.part) {
+                    return;
+                }
+                let start = Instant::now();
+                let answer = f().to_string();
+                let part_time = start.elapsed();
+                let status = match &self.first {
+                    Some((first_variant, first)) if *first != answer => {
+                        FAILED.store(true, Ordering::Relaxed);
+                        Status::Mismatch {
+                            answer,
+                            expected: first.clone(),
+                            expected_variant: *first_variant,
+                        }
+                    }
+                    Some(_) => Status::Ok(answer),
+                    None => {
+                        self.first = Some((variant, answer.clone()));
+                        Status::Ok(answer)
+                    }
+                };
+                Record {
+                    year: self.year,
+                    day: self.day,
+                    part: self.part,
+                    variant,
+                    status,
+                    parse_time: self.parse_time,
+                    part_time: Some(part_time),
+                }
+                .report(self.opt.format);
+            }
+        }
+
+        /// Report both parts of a day as failed because the input could not be parsed, which fails
+        /// the run.
+        pub fn parse_failed(opt: &Opt, year: u16, day: u8, parse_time: Duration, error: &Error) {
+            FAILED.store(true, Ordering::Relaxed);
+            for part in (1..=2).filter(|part| part_selected(opt, *part)) {
+                Record {
+                    year,
+                    day,
+                    part,
+                    variant: None,
+                    status: Status::Error(format!("cannot parse input: {:#}", error)),
+                    parse_time,
+                    part_time: None,
+                }
+                .report(opt.format);
+            }
+        }
+
+        /// Report both parts of a day as skipped because there is no input.
+        pub fn no_input(opt: &Opt, year: u16, day: u8) {
+            for part in (1..=2).filter(|part| part_selected(opt, *part)) {
+                Record {
+                    year,
+                    day,
+                    part,
+                    variant: None,
+                    status: Status::NoInput,
+                    parse_time: Duration::ZERO,
+                    part_time: None,
+                }
+                .report(opt.format);
+            }
+        }
+    )
+}
+
+/// The function that reads the input for the given day and runs both parts.
+pub fn day_runner(settings: &Settings, year: u16, day: u8) -> Result<TokenStream> {
+    let day_mod = format_ident!("day{}", day);
+    let day_fn = format_ident!("run_day{}", day);
+    let runner = runner_path(settings);
+    // Paths are relative to the project root, so the generated code works wherever the project is.
+    let rel_path = input_rel_path(year, day);
+    let path = format!("/{}", rel_path);
+    let path = quote!(concat!(env!("CARGO_MANIFEST_DIR"), #path));
+    let input = match settings.input_mode {
+        // `include_str!` fails the build if the file is missing, e.g. for a day file added by hand,
+        // so embed no input instead. The day is then reported as having none.
+        InputMode::Embed if !settings.project_root.join(&rel_path).is_file() => {
+            quote!(#runner::Input::Embedded(""))
+        }
+        InputMode::Embed => quote!(#runner::Input::Embedded(include_str!(#path))),
+        InputMode::Runtime => quote!(#runner::Input::File(#path)),
+    };
+    let source = settings.day_source(year, day);
+    let info = DayInfo::load(&source, year, day)?;
+    for warning in &info.warnings {
+        settings.warn(&format!("in {}: {}", source.display(), warning));
+    }
+    let parse_fn = info.parse_fn.as_ref().map(|name| format_ident!("{}", name));
+    let parse = match info.parse {
+        ParseStyle::Lines => quote!(input
+            .lines()
+            .map(#day_mod::#parse_fn)
+            .collect::<Result<Vec<_>>>()),
+        ParseStyle::Whole => quote!(#day_mod::#parse_fn(&input)),
+        ParseStyle::Paragraphs => quote!(input
+            .trim_end()
+            .split("\n\n")
+            .map(#day_mod::#parse_fn)
+            .collect::<Result<Vec<_>>>()),
+        ParseStyle::FromStr => quote!(input
+            .parse::<#day_mod::Input>()
+            .map_err(|e| format_err!("{}", e))),
+    };
+
+    // Each part runs all of its solutions, so their answers can be compared.
+    let parts = [1u8, 2].into_iter().filter_map(|part| {
+        let solutions = info
+            .solutions
+            .iter()
+            .filter(|solution| solution.part == part)
+            .map(|solution| {
+                let fn_name = format_ident!("{}", solution.fn_name);
+                let variant = match &solution.name {
+                    Some(name) => quote!(Some(#name)),
+                    None => quote!(None),
+                };
+                quote!(part.run(#variant, || #day_mod::#fn_name(&parsed));)
+            })
+            .collect::<Vec<_>>();
+        if solutions.is_empty() {
+            return None;
+        }
+        Some(quote!({
+            let mut part = #runner::Part::new(opt, #year, #day, #part, parse_time);
+            #(#solutions)*
+        }))
+    });
+
+    Ok(quote!(
+        fn #day_fn(opt: &#runner::Opt) -> Result {
+            log::info!("Running day {}", #day);
+            let input = match #runner::input(opt, #input)? {
+                Some(input) => input,
+                None => {
+                    #runner::no_input(opt, #year, #day);
+                    return Ok(());
+                }
+            };
+            let start = Instant::now();
+            let parsed = #parse;
+            let parse_time = start.elapsed();
+            let parsed = match parsed {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    #runner::parse_failed(opt, #year, #day, parse_time, &e);
+                    return Ok(());
+                }
+            };
+            #(#parts)*
+            Ok(())
+        }
+    ))
+}
+
+/// The path to the runner items from generated code.
+pub fn runner_path(settings: &Settings) -> TokenStream {
+    match settings.layout {
+        Layout::Modules => quote!(crate),
+        Layout::Bins => {
+            let lib = lib_ident();
+            quote!(#lib::runner)
+        }
+    }
+}
+
+/// The name of the attributes' crate in Rust code.
+pub fn macros_ident() -> Ident {
+    format_ident!("{}", MACROS_CRATE.replace('-', "_"))
+}
+
+/// The name of the generated project's library crate.
+pub fn lib_ident() -> Ident {
+    format_ident!("{}", PROJECT_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn macros_version() {
+        let manifest: toml::Value =
+            toml::from_str(include_str!("../../cargo-aoc-macros/Cargo.toml")).unwrap();
+        assert_eq!(
+            manifest["package"]["version"].as_str(),
+            Some(MACROS_VERSION)
+        );
+    }
+
+    #[test]
+    fn items_are_separated() {
+        let code = format(quote! {
+            use std::fmt;
+            use std::str::FromStr;
+            mod a;
+            #[cfg(test)]
+            mod b;
+            pub struct Line;
+            pub fn parse(input: &str) -> Vec<Line> {
+                input.lines().map(|_| Line).collect()
+            }
+            pub type Input = Vec<Line>;
+            aoc_main!();
+            fn f<T>(t: T) -> T
+            where
+                T: Copy,
+            {
+                t
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            code,
+            "use std::fmt;\n\
+            use std::str::FromStr;\n\
+            \n\
+            mod a;\n\
+            #[cfg(test)]\n\
+            mod b;\n\
+            \n\
+            pub struct Line;\n\
+            \n\
+            pub fn parse(input: &str) -> Vec<Line> {\n    input.lines().map(|_| Line).collect()\n}\n\
+            \n\
+            pub type Input = Vec<Line>;\n\
+            \n\
+            aoc_main!();\n\
+            \n\
+            fn f<T>(t: T) -> T\n\
+            where\n    T: Copy,\n\
+            {\n    t\n}\n"
+        );
+    }
+
+    #[test]
+    fn years() {
+        let year = |name: &str| parse_year(OsStr::new(name), "_");
+        assert_eq!(year("_2021"), Some(2021));
+        assert_eq!(year("_2015"), Some(2015));
+        for name in [
+            "_2014", "_02021", "old_2021", "_2021.rs", "_", "2021", "_70000",
+        ] {
+            assert_eq!(year(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn days() {
+        let day = |name: &str| parse_day(OsStr::new(name), "day", ".rs");
+        assert_eq!(day("day1.rs"), Some(1));
+        assert_eq!(day("day25.rs"), Some(25));
+        for name in [
+            "oldday1.rs",
+            "day01.rs",
+            "day0.rs",
+            "day26.rs",
+            // Too big for a `u8`.
+            "day300.rs",
+            "day+1.rs",
+            "day1.rs.bak",
+            "day.rs",
+        ] {
+            assert_eq!(day(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn exact_numbers() {
+        let exact = |name: &str| parse_exact::<u8>(OsStr::new(name), "input", ".txt");
+        assert_eq!(exact("input0.txt"), Some(0));
+        assert_eq!(exact("input255.txt"), Some(255));
+        assert_eq!(exact("input256.txt"), None);
+        assert_eq!(exact("input-1.txt"), None);
+        assert_eq!(exact("input 1.txt"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn names_that_are_not_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        assert_eq!(
+            parse_day(OsStr::from_bytes(b"day1\xff.rs"), "day", ".rs"),
+            None
+        );
+        assert_eq!(parse_year(OsStr::from_bytes(b"_2021\xff"), "_"), None);
+    }
+}
@@ -0,0 +1,106 @@
+//! Attributes for registering Advent of Code solutions with `cargo-aoc`.
+//!
+//! ```ignore
+//! #[aoc::parse(2022, 5)]
+//! pub fn parse(input: &str) -> Result<Crates> { .. }
+//!
+//! #[aoc::part(2022, 5, 1)]
+//! pub fn part1(crates: &Crates) -> String { .. }
+//!
+//! #[aoc::part(2022, 5, 2, name = "fast")]
+//! pub fn part2_fast(crates: &Crates) -> String { .. }
+//! ```
+//!
+//! The attributes leave the functions they are on unchanged. `cargo-aoc` finds them when it
+//! generates the code that runs the solutions; here we only check their arguments, so that
+//! mistakes are reported by the compiler.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use syn::{parse_macro_input, AttributeArgs, ItemFn, Lit, Meta, NestedMeta, Visibility};
+
+/// Register the function that parses the whole input for a day: `#[aoc::parse(year, day)]`.
+#[proc_macro_attribute]
+pub fn parse(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as AttributeArgs);
+    check(&args, 2, &["year", "day"], item)
+}
+
+/// Register a solution for a part: `#[aoc::part(year, day, part)]`.
+///
+/// Add `name = "..."` to register more than one solution for the same part.
+#[proc_macro_attribute]
+pub fn part(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as AttributeArgs);
+    check(&args, 3, &["year", "day", "part", "name"], item)
+}
+
+/// Check the arguments, and pass the item through unchanged.
+///
+/// Errors are added in front of the item rather than replacing it, so that the function still
+/// exists and doesn't cause a second round of errors wherever it is used.
+fn check(args: &[NestedMeta], positional: usize, names: &[&str], item: TokenStream) -> TokenStream {
+    let result = check_args(args, positional, names)
+        .and_then(|()| syn::parse::<ItemFn>(item.clone()))
+        .and_then(|item| match item.vis {
+            Visibility::Public(_) => Ok(()),
+            _ => Err(syn::Error::new_spanned(
+                item.sig.fn_token,
+                "solutions must be `pub` so that cargo-aoc can call them",
+            )),
+        });
+    match result {
+        Ok(()) => item,
+        Err(e) => {
+            let mut tokens = TokenStream::from(e.to_compile_error());
+            tokens.extend(item);
+            tokens
+        }
+    }
+}
+
+fn check_args(args: &[NestedMeta], positional: usize, names: &[&str]) -> syn::Result<()> {
+    let usage = || {
+        let mut usage = names[..positional].join(", ");
+        if names.len() > positional {
+            usage.push_str(", name = \"...\"");
+        }
+        format!("expected `{}`", usage)
+    };
+    if args.len() < positional {
+        return Err(syn::Error::new(Span::call_site(), usage()));
+    }
+
+    for (arg, name) in args[..positional].iter().zip(names) {
+        let value = match arg {
+            NestedMeta::Lit(Lit::Int(lit)) => lit.base10_parse::<u16>()?,
+            _ => return Err(syn::Error::new_spanned(arg, usage())),
+        };
+        let valid = match *name {
+            "year" => value >= 2015,
+            "day" => (1..=25).contains(&value),
+            "part" => (1..=2).contains(&value),
+            _ => unreachable!(),
+        };
+        if !valid {
+            return Err(syn::Error::new_spanned(
+                arg,
+                format!("{} {} is out of range", name, value),
+            ));
+        }
+    }
+
+    for arg in &args[positional..] {
+        match arg {
+            NestedMeta::Meta(Meta::NameValue(nv))
+                if names[positional..].iter().any(|name| nv.path.is_ident(name)) =>
+            {
+                if !matches!(nv.lit, Lit::Str(_)) {
+                    return Err(syn::Error::new_spanned(&nv.lit, "expected a string"));
+                }
+            }
+            _ => return Err(syn::Error::new_spanned(arg, usage())),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,208 @@
+//! Building and running the generated project with cargo.
+
+use crate::{config::PROJECT_NAME, Config, Error};
+use cargo_aoc_codegen as codegen;
+use qu::ick_use::*;
+use serde::Deserialize;
+use std::{
+    collections::BTreeSet,
+    env,
+    ffi::OsString,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+/// Which solutions to run.
+#[derive(Debug, Default, Clone)]
+pub struct Selection {
+    pub year: Option<u16>,
+    pub day: Option<u8>,
+    pub part: Option<u8>,
+    /// Run against this file instead of the downloaded input.
+    pub input: Option<PathBuf>,
+}
+
+impl Selection {
+    /// The arguments to pass to the generated runner.
+    pub fn args(&self) -> Vec<OsString> {
+        let mut args = vec![];
+        if let Some(year) = self.year {
+            args.extend(["--year".into(), year.to_string().into()]);
+        }
+        if let Some(day) = self.day {
+            args.extend(["--day".into(), day.to_string().into()]);
+        }
+        if let Some(part) = self.part {
+            args.extend(["--part".into(), part.to_string().into()]);
+        }
+        if let Some(input) = &self.input {
+            args.extend(["--input".into(), input.into()]);
+        }
+        args
+    }
+}
+
+/// Build the main binary of the generated project, and return its path.
+///
+/// If a year is given, only the feature for that year is enabled. Compiler messages are logged,
+/// and errors are attributed to the day source file they come from where possible.
+pub fn build(config: &Config, year: Option<u16>, release: bool) -> Result<PathBuf, Error> {
+    cargo_build(config, year, release).map_err(Error::Build)
+}
+
+fn cargo_build(config: &Config, year: Option<u16>, release: bool) -> Result<PathBuf> {
+    let mut cmd = cargo();
+    cmd.current_dir(&config.project_root)
+        .args(["build", "--message-format", "json", "--bin", PROJECT_NAME])
+        .stdout(Stdio::piped());
+    if let Some(year) = year {
+        cmd.args(["--no-default-features", "--features"])
+            .arg(codegen::year_feature(year));
+    }
+    if release {
+        cmd.arg("--release");
+    }
+    log::info!("Building solutions");
+    let mut child = cmd.spawn().context("cannot run cargo")?;
+
+    let mut executable = None;
+    let mut failed_days = BTreeSet::new();
+    let stdout = BufReader::new(child.stdout.take().unwrap());
+    for line in stdout.lines() {
+        let line = line?;
+        match serde_json::from_str(&line) {
+            Ok(BuildOutput::CompilerArtifact {
+                target,
+                executable: Some(path),
+            }) if target.name == PROJECT_NAME => executable = Some(path),
+            Ok(BuildOutput::CompilerMessage { message }) => {
+                if let Some(day) = report_diagnostic(&message) {
+                    failed_days.insert(day);
+                }
+            }
+            Ok(_) => (),
+            // Not all of cargo's output is JSON, pass the rest through.
+            Err(_) => println!("{}", line),
+        }
+    }
+    let status = child.wait().context("cannot run cargo")?;
+
+    if !failed_days.is_empty() {
+        let days = failed_days
+            .iter()
+            .map(|(year, day)| format!("year {}, day {}", year, day))
+            .collect::<Vec<_>>();
+        bail!("build failed in {}", days.join("; "));
+    }
+    ensure!(status.success(), "build failed ({})", status);
+    executable.context("cargo did not report the path of the built binary")
+}
+
+/// Run the built binary, passing the selection through.
+pub fn run(config: &Config, executable: &Path, selection: &Selection) -> Result<(), Error> {
+    run_solutions(config, executable, selection).map_err(Error::Build)
+}
+
+fn run_solutions(config: &Config, executable: &Path, selection: &Selection) -> Result {
+    let status = Command::new(executable)
+        .current_dir(&config.project_root)
+        .args(selection.args())
+        .status()
+        .with_context(|| format!("cannot run {}", executable.display()))?;
+    ensure!(status.success(), "running solutions failed ({})", status);
+    Ok(())
+}
+
+/// The cargo that invoked us, or the one on the path.
+fn cargo() -> Command {
+    Command::new(env::var_os("CARGO").unwrap_or_else(|| "cargo".into()))
+}
+
+/// Log a compiler message, along with the day it came from.
+///
+/// Returns the year and day if the message is an error in a day's source file.
+fn report_diagnostic(diagnostic: &Diagnostic) -> Option<(u16, u8)> {
+    let rendered = diagnostic.rendered.as_deref().unwrap_or(&diagnostic.message);
+    let day = diagnostic
+        .spans
+        .iter()
+        .find(|span| span.is_primary)
+        .and_then(|span| day_for_file(&span.file_name));
+    let location = match day {
+        Some((year, day)) => format!("in year {}, day {}:\n", year, day),
+        None => String::new(),
+    };
+    match diagnostic.level.as_str() {
+        "error" | "error: internal compiler error" => {
+            log::error!("{}{}", location, rendered);
+            day
+        }
+        "warning" => {
+            log::warn!("{}{}", location, rendered);
+            None
+        }
+        _ => {
+            log::info!("{}{}", location, rendered);
+            None
+        }
+    }
+}
+
+/// Get the year and day for a source file, if it is a day's source file.
+fn day_for_file(file_name: &str) -> Option<(u16, u8)> {
+    let path = Path::new(file_name);
+    let day = codegen::parse_day(path.file_name()?, "day", ".rs")?;
+    let year = codegen::parse_year(path.parent()?.file_name()?, "_")?;
+    Some((year, day))
+}
+
+/// A line of output from `cargo build --message-format json`.
+#[derive(Deserialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+enum BuildOutput {
+    CompilerArtifact {
+        target: Target,
+        executable: Option<PathBuf>,
+    },
+    CompilerMessage {
+        message: Diagnostic,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct Target {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct Diagnostic {
+    message: String,
+    level: String,
+    rendered: Option<String>,
+    spans: Vec<Span>,
+}
+
+#[derive(Deserialize)]
+struct Span {
+    file_name: String,
+    is_primary: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn day_files() {
+        assert_eq!(day_for_file("src/_2021/day1.rs"), Some((2021, 1)));
+        assert_eq!(day_for_file("/aoc/src/_2015/day25.rs"), Some((2015, 25)));
+        assert_eq!(day_for_file("src/_2021/oldday1.rs"), None);
+        assert_eq!(day_for_file("src/_2021/day26.rs"), None);
+        assert_eq!(day_for_file("src/old_2021/day1.rs"), None);
+        assert_eq!(day_for_file("src/main.rs"), None);
+        assert_eq!(day_for_file("day1.rs"), None);
+    }
+}
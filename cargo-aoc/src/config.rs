@@ -7,16 +7,15 @@ use cargo::{
 use qu::ick_use::*;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{BTreeMap, BTreeSet},
     env, fs,
-    io::prelude::*,
+    io::{self, prelude::*},
     path::{Path, PathBuf},
 };
 
-use crate::IoResultExt;
+use crate::{util::IoResultExt, Error};
 
-const PROJECT_NAME: &str = "aoc";
-const CONFIG_PATH: &str = ".aoc.toml";
+pub use cargo_aoc_codegen::{InputMode, Layout, PROJECT_NAME};
+use cargo_aoc_codegen::{Settings, CONFIG_PATH};
 
 #[derive(Debug)]
 pub struct Config {
@@ -24,11 +23,13 @@ pub struct Config {
     pub config_path: PathBuf,
     /// The contents of the `.aoc.toml` config file.
     pub file: AocConfig,
+    /// Overwrite generated files even if they have been edited by hand. Set with `--force`.
+    pub force: bool,
 }
 
 impl Config {
     /// Create a new project and return the config
-    pub fn create_project() -> Result<Self> {
+    pub fn create_project() -> Result<Self, Error> {
         fn inner() -> Result<Config> {
             let project_root = env::current_dir()?.join(PROJECT_NAME);
 
@@ -55,42 +56,114 @@ impl Config {
                 project_root,
                 config_path,
                 file,
+                force: false,
             })
         }
-        inner().context("cannot create new project")
+        inner()
+            .context("cannot create new project")
+            .map_err(Error::Project)
     }
 
-    pub fn load() -> Result<Self> {
-        let project_root =
-            find_project_dir().context("cannot load project at the current location")?;
+    /// Load the project the current directory is in.
+    pub fn load() -> Result<Self, Error> {
+        const CONTEXT: &str = "cannot load project at the current location";
+        let project_root = find_project_dir()
+            .context(CONTEXT)
+            .map_err(Error::Project)?;
         let config_path = project_root.join(CONFIG_PATH);
-        let file =
-            AocConfig::load(&config_path).context("cannot load project at the current location")?;
+        let file = AocConfig::load(&config_path)
+            .context(CONTEXT)
+            .map_err(|e| {
+                // A cargo project without our config file isn't one of ours.
+                let missing = e
+                    .root_cause()
+                    .downcast_ref::<io::Error>()
+                    .is_some_and(|e| e.kind() == io::ErrorKind::NotFound);
+                if missing {
+                    Error::Project(e)
+                } else {
+                    Error::Config(e)
+                }
+            })?;
         Ok(Config {
             project_root,
             config_path,
             file,
+            force: false,
         })
     }
 
-    /// The folder for the source files for year given.
+    /// The folder for the source files for the given year, see [`Settings::year_folder`].
     pub fn year_folder(&self, year: u16) -> PathBuf {
-        self.project_root.join(format!("src/_{}", year))
+        self.codegen().year_folder(year)
     }
 
-    /// The source file for the given year and day
+    /// The source file for the given year and day, see [`Settings::day_source`].
     pub fn day_source(&self, year: u16, day: u8) -> PathBuf {
+        self.codegen().day_source(year, day)
+    }
+
+    /// The binary for the given year and day, when using the bins layout.
+    pub fn day_bin(&self, year: u16, day: u8) -> PathBuf {
         self.project_root
-            .join(format!("src/_{}/day{}.rs", year, day))
+            .join(format!("src/bin/{}.rs", Self::day_bin_name(year, day)))
+    }
+
+    /// The name of the binary target for the given year and day, when using the bins layout.
+    pub fn day_bin_name(year: u16, day: u8) -> String {
+        format!("{}_day{:02}", year, day)
     }
 
+    /// The input file for the given year and day, see [`cargo_aoc_codegen::input_rel_path`].
     pub fn input_path(&self, year: u16, day: u8) -> PathBuf {
         self.project_root
-            .join(format!("input/{}/input{}.txt", year, day))
+            .join(cargo_aoc_codegen::input_rel_path(year, day))
+    }
+
+    /// An example input for the given year and day, e.g. from the puzzle description.
+    pub fn example_path(&self, year: u16, day: u8) -> PathBuf {
+        self.project_root
+            .join(format!("input/{}/example{}.txt", year, day))
+    }
+
+    /// The puzzle description for the given year and day, as downloaded from the website.
+    pub fn puzzle_path(&self, year: u16, day: u8) -> PathBuf {
+        self.project_root
+            .join(format!("input/{}/puzzle{}.html", year, day))
+    }
+
+    /// A user-defined template for day source files, e.g. `.aoc/templates/day.rs`.
+    pub fn template_path(&self, name: &str) -> PathBuf {
+        self.project_root
+            .join(format!(".aoc/templates/{}.rs", name))
+    }
+
+    /// The stars the website showed for each day at the last sync, `.aoc/stars.toml`.
+    pub fn stars_path(&self) -> PathBuf {
+        self.project_root.join(".aoc/stars.toml")
+    }
+
+    /// The settings that affect the generated code.
+    pub fn codegen(&self) -> Settings {
+        Settings {
+            project_root: self.project_root.clone(),
+            input_mode: self.file.input_mode,
+            layout: self.file.layout,
+            in_build_script: false,
+        }
     }
 
-    pub fn save(self) -> Result {
-        self.file.save(&*self.config_path)
+    /// The session cookie, which is needed to download anything specific to the user.
+    pub fn cookie(&self) -> Result<&str, Error> {
+        self.file
+            .cookie
+            .as_deref()
+            .context("you need to store your cookie before you can download input")
+            .map_err(Error::Auth)
+    }
+
+    pub fn save(self) -> Result<(), Error> {
+        self.file.save(&*self.config_path).map_err(Error::Config)
     }
 }
 
@@ -101,11 +174,18 @@ impl Drop for Config {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct AocConfig {
     pub cookie: Option<String>,
-    /// Keeps track of the years we have downloaded for.
-    pub years: BTreeMap<u16, BTreeSet<u8>>,
+    /// How the generated code gets hold of puzzle input.
+    #[serde(default)]
+    pub input_mode: InputMode,
+    /// How the generated code is laid out.
+    #[serde(default)]
+    pub layout: Layout,
+    /// Whether the runner is generated by the project's build script, rather than by us.
+    #[serde(default)]
+    pub build_script: bool,
 }
 
 impl AocConfig {
@@ -140,15 +220,6 @@ impl AocConfig {
     }
 }
 
-impl Default for AocConfig {
-    fn default() -> Self {
-        Self {
-            cookie: None,
-            years: BTreeMap::new(),
-        }
-    }
-}
-
 fn find_project_dir() -> Result<PathBuf> {
     let cwd = std::env::current_dir().context("cannot find project dir")?;
     let cargo_toml = find_root_manifest_for_wd(&cwd).context("cannot find project dir")?;
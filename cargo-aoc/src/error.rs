@@ -0,0 +1,143 @@
+//! The errors returned by the library.
+
+use std::{error::Error as StdError, fmt, io};
+
+/// An error, sorted by what failed.
+///
+/// Each variant holds the whole error, with the context that explains it, so it is shown the same
+/// way `cargo-aoc` shows it. `cargo-aoc` exits with a different code for each variant, see
+/// [`Error::exit_code`].
+#[derive(Debug)]
+pub enum Error {
+    /// The arguments can't be parsed, or don't make sense, e.g. day 26.
+    Usage(anyhow::Error),
+    /// There is no project here, e.g. the current directory isn't in one, or a new one can't be
+    /// created.
+    Project(anyhow::Error),
+    /// The project's config file can't be read or saved.
+    Config(anyhow::Error),
+    /// Reading or writing the project's files failed.
+    Io(anyhow::Error),
+    /// There is no session cookie, or the website doesn't accept it.
+    Auth(anyhow::Error),
+    /// The website is limiting how often we can make requests.
+    RateLimit(anyhow::Error),
+    /// The puzzle hasn't been released yet.
+    NotReleased(anyhow::Error),
+    /// Talking to the Advent of Code website failed for any other reason.
+    Network(anyhow::Error),
+    /// Generating the code that runs the solutions failed, e.g. because a solution has the wrong
+    /// signature.
+    Codegen(anyhow::Error),
+    /// Building or running the solutions failed.
+    Build(anyhow::Error),
+}
+
+impl Error {
+    /// The error itself, whatever failed.
+    pub fn inner(&self) -> &anyhow::Error {
+        match self {
+            Error::Usage(e)
+            | Error::Project(e)
+            | Error::Config(e)
+            | Error::Io(e)
+            | Error::Auth(e)
+            | Error::RateLimit(e)
+            | Error::NotReleased(e)
+            | Error::Network(e)
+            | Error::Codegen(e)
+            | Error::Build(e) => e,
+        }
+    }
+
+    /// A name for what failed, e.g. `auth` or `rate-limit`, as it appears in reports.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Usage(_) => "usage",
+            Error::Project(_) => "project",
+            Error::Config(_) => "config",
+            Error::Io(_) => "io",
+            Error::Auth(_) => "auth",
+            Error::RateLimit(_) => "rate-limit",
+            Error::NotReleased(_) => "not-released",
+            Error::Network(_) => "network",
+            Error::Codegen(_) => "codegen",
+            Error::Build(_) => "build",
+        }
+    }
+
+    /// The code `cargo-aoc` exits with for this error.
+    ///
+    /// | Code | Error                    |
+    /// |------|--------------------------|
+    /// | 1    | anything not sorted here |
+    /// | 2    | [`Error::Usage`]         |
+    /// | 3    | [`Error::Project`]       |
+    /// | 4    | [`Error::Config`]        |
+    /// | 5    | [`Error::Io`]            |
+    /// | 6    | [`Error::Auth`]          |
+    /// | 7    | [`Error::RateLimit`]     |
+    /// | 8    | [`Error::NotReleased`]   |
+    /// | 9    | [`Error::Network`]       |
+    /// | 10   | [`Error::Codegen`]       |
+    /// | 11   | [`Error::Build`]         |
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Usage(_) => 2,
+            Error::Project(_) => 3,
+            Error::Config(_) => 4,
+            Error::Io(_) => 5,
+            Error::Auth(_) => 6,
+            Error::RateLimit(_) => 7,
+            Error::NotReleased(_) => 8,
+            Error::Network(_) => 9,
+            Error::Codegen(_) => 10,
+            Error::Build(_) => 11,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e.into())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.inner(), f)
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.inner().source()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes() {
+        // Scripts rely on these, so they must not change.
+        let e = || anyhow::format_err!("failed");
+        let errors = [
+            (Error::Usage(e()), "usage", 2),
+            (Error::Project(e()), "project", 3),
+            (Error::Config(e()), "config", 4),
+            (Error::Io(e()), "io", 5),
+            (Error::Auth(e()), "auth", 6),
+            (Error::RateLimit(e()), "rate-limit", 7),
+            (Error::NotReleased(e()), "not-released", 8),
+            (Error::Network(e()), "network", 9),
+            (Error::Codegen(e()), "codegen", 10),
+            (Error::Build(e()), "build", 11),
+        ];
+        for (error, kind, code) in errors {
+            assert_eq!(error.kind(), kind);
+            assert_eq!(error.exit_code(), code, "{}", kind);
+        }
+    }
+}
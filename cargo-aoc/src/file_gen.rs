@@ -1,125 +1,248 @@
-use crate::{config::Config, input, util, Result};
+use crate::{
+    config::{Config, Layout},
+    input::{self, InputShape},
+    project::Project,
+    puzzle::Puzzle,
+    report::Report,
+    user_regions,
+    util::{self, IoResultExt},
+    web,
+};
 use qu::ick_use::*;
+use cargo_aoc_codegen as codegen;
+use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
-use regex::Regex;
-use rustfmt_wrapper::rustfmt;
-use std::{collections::BTreeSet, fmt, fs};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
-pub fn build_main_rs(config: &Config) -> Result {
-    let years: Vec<_> = input::get_years(config)?.collect();
+/// Generated files waiting to be written.
+///
+/// Every file is checked for changes made by hand before any of them are written, so a file that
+/// can't be overwritten doesn't leave the project half regenerated.
+#[derive(Debug, Default)]
+pub struct AutoFiles {
+    files: Vec<(PathBuf, String)>,
+}
+
+impl AutoFiles {
+    /// Add a file from `auto_file`, to be written with the others.
+    fn add(&mut self, path: PathBuf, content: String) {
+        self.files.push((path, content));
+    }
+
+    /// Fail if writing the files would lose changes made to them by hand, without writing
+    /// anything.
+    pub fn check(&self, config: &Config) -> Result {
+        for (path, content) in &self.files {
+            fill_auto_file(config, path, content)?;
+        }
+        Ok(())
+    }
+
+    /// Write the files, keeping the contents of the user regions in the existing ones.
+    pub fn write(self, config: &Config, report: &mut Report) -> Result {
+        let files = self
+            .files
+            .into_iter()
+            .map(|(path, content)| Ok((fill_auto_file(config, &path, &content)?, path)))
+            .collect::<Result<Vec<_>>>()?;
+        for (content, path) in files {
+            fs::create_dir_all(path.parent().unwrap())?;
+            let written = util::write_if_changed(&path, content)?;
+            report.file(path, written);
+        }
+        Ok(())
+    }
+}
+
+pub fn build_main_rs(config: &Config, project: &Project, files: &mut AutoFiles) -> Result {
+    let settings = config.codegen();
+    let years: Vec<_> = project.source_years().collect();
     let year_idents = years
         .iter()
         .map(|year| format_ident!("_{}", year))
         .collect::<Vec<_>>();
+    // `update_manifest` makes sure every year has a feature.
+    let year_gates = years
+        .iter()
+        .map(|year| {
+            let feature = codegen::year_feature(*year);
+            quote!(#[cfg(feature = #feature)])
+        })
+        .collect::<Vec<_>>();
+    // With the bins layout the runner lives in the library, so the day binaries can share it.
+    let runner = match config.file.layout {
+        Layout::Modules if project.uses_attributes() => {
+            let macros = codegen::macros_ident();
+            let runner = codegen::runner_items(user_regions::field_placeholder("opt"));
+            quote!(
+                extern crate #macros as aoc;
+
+                #runner
+            )
+        }
+        Layout::Modules => codegen::runner_items(user_regions::field_placeholder("opt")),
+        Layout::Bins => {
+            let lib = codegen::lib_ident();
+            quote!(use #lib::runner::Opt;)
+        }
+    };
+
+    let imports = user_regions::placeholder("imports");
+    let items = user_regions::placeholder("items");
+    let main = codegen::main_fn(
+        &settings,
+        &years,
+        &year_gates,
+        user_regions::placeholder("main"),
+    );
 
     let content = auto_file(quote!(
         use qu::ick_use::*;
+        #imports
 
-        #(mod #year_idents;)*
-
-        #[derive(StructOpt)]
-        struct Opt {
-            /// Specify the year you want to run.
-            ///
-            /// Defaults to all years
-            #[structopt(long, short)]
-            year: Option<u16>,
-            /// Specify the day you want to run.
-            ///
-            /// Defaults to all the days.
-            #[structopt(long, short)]
-            day: Option<u8>,
-        }
+        #(
+            #year_gates
+            mod #year_idents;
+        )*
 
-        #[qu::ick]
-        fn main(opt: Opt) {
-            match opt.year {
-                Some(year) => {
-                    #(
-                        if #years == year {
-                            crate::#year_idents::run(opt.day)?;
-                        }
-                    )*
-                },
-                None => {
-                    log::info!("Running all available solutions");
-                    #(crate::#year_idents::run(opt.day)?;)*
-                }
-            }
-            Ok(())
-        }
-    ));
+        #runner
 
-    fs::write(config.project_root.join("src/main.rs"), content)?;
+        #items
 
+        #main
+    ))?;
+
+    files.add(config.project_root.join("src/main.rs"), content);
     Ok(())
 }
 
-pub fn build_mod_file(config: &Config, year: u16) -> Result {
-    let mut days_present = BTreeSet::new();
-    let regex = Regex::new(r"day(\d+)\.rs$").unwrap();
-    let year_folder = config.year_folder(year);
-    for file in fs::read_dir(&year_folder)? {
-        let file = file?;
-        if let Some(caps) = regex.captures(file.path().to_str().context("found non-utf8 file")?) {
-            days_present.insert(caps.get(1).unwrap().as_str().parse::<u8>()?);
+/// Create `build.rs` and `src/main.rs` for a new project whose runner is generated by its build
+/// script.
+///
+/// `src/main.rs` only includes the generated code, so like `build.rs` it is never regenerated.
+pub fn build_build_script(config: &Config, report: &mut Report) -> Result {
+    let build_rs = gen_file(quote!(
+        fn main() {
+            cargo_aoc_codegen::build().expect("cannot generate the code that runs the solutions");
         }
+    ))?;
+    let path = config.project_root.join("build.rs");
+    fs::write(&path, build_rs)?;
+    report.file(path, true);
+
+    let main_rs = gen_text(
+        "//! Runs the solutions. The code that finds them is generated by `build.rs`.\n\n\
+        include!(concat!(env!(\"OUT_DIR\"), \"/aoc.rs\"));\n",
+    );
+    let path = config.project_root.join("src/main.rs");
+    fs::write(&path, main_rs)?;
+    report.file(path, true);
+    Ok(())
+}
+
+/// Create `src/lib.rs` for the bins layout, only if it is not already present.
+pub fn build_lib_rs(config: &Config, report: &mut Report) -> Result {
+    let filename = config.project_root.join("src/lib.rs");
+    if util::path_exists(&filename)? {
+        report.file(filename, false);
+        return Ok(());
     }
 
-    let days = days_present.iter().collect::<Vec<_>>();
-    let days_mods = days_present
-        .iter()
-        .map(|day| format_ident!("day{}", day))
-        .collect::<Vec<_>>();
+    let content = gen_text(
+        "//! Code shared by all the solutions. Add your helpers here.\n\n\
+        pub mod runner;\n\n\
+        // Makes `#[aoc::parse]` and `#[aoc::part]` available to the solutions. There is nothing\n\
+        // to import until a solution uses them.\n\
+        #[allow(unused_imports)]\n\
+        pub use runner::macros::*;\n",
+    );
 
-    let input_paths = days_present
-        .iter()
-        .map(|day| {
-            config
-                .input_path(year, *day)
-                .to_str()
-                .map(ToOwned::to_owned)
-                .context("non-utf8 path")
-        })
-        .collect::<Result<Vec<_>>>()?;
+    fs::write(&filename, content)?;
+    report.file(filename, true);
+    Ok(())
+}
 
+/// Write `src/runner.rs` for the bins layout, which holds the code shared by all the binaries.
+pub fn build_runner_rs(config: &Config, project: &Project, files: &mut AutoFiles) -> Result {
+    let runner = codegen::runner_items(user_regions::field_placeholder("opt"));
+    let macros = if project.uses_attributes() {
+        let macros = codegen::macros_ident();
+        quote!(pub use #macros::{parse, part};)
+    } else {
+        quote!()
+    };
     let content = auto_file(quote!(
         use qu::ick_use::*;
 
-        #(mod #days_mods;)*
-
-        pub fn run(day: Option<u8>) -> Result {
-            match day {
-                Some(day) => {
-                    #(
-                        if day == #days {
-                            log::info!("Running day {}", #days);
-                            let parsed = include_str!(#input_paths).lines().map(#days_mods::parse).collect::<Result<Vec<_>>>()?;
-                            log::info!("  Part 1 result: {}", #days_mods::part1(&parsed));
-                            log::info!("  Part 2 result: {}", #days_mods::part2(&parsed));
-                        }
-                    )*
-                },
-                None => {
-                    #(
-                        log::info!("Running day {}", #days);
-                        let parsed = include_str!(#input_paths).lines().map(#days_mods::parse).collect::<Result<Vec<_>>>()?;
-                        log::info!("  Part 1 result: {}", #days_mods::part1(&parsed));
-                        log::info!("  Part 2 result: {}", #days_mods::part2(&parsed));
-                    )*
-                }
-            }
-            Ok(())
+        /// The attributes for registering solutions, once a solution uses them.
+        pub mod macros {
+            #macros
+        }
+
+        #runner
+    ))?;
+
+    files.add(config.project_root.join("src/runner.rs"), content);
+    Ok(())
+}
+
+pub fn build_mod_file(
+    config: &Config,
+    project: &Project,
+    year: u16,
+    files: &mut AutoFiles,
+) -> Result {
+    let items = codegen::year_items(&config.codegen(), year, &project.source_days(year), false)?;
+    let content = auto_file(items)?;
+    files.add(config.year_folder(year).join("mod.rs"), content);
+    Ok(())
+}
+
+/// Write the binary for the given year and day, for the bins layout.
+///
+/// The binary includes the day's source file as a module, so it is shared with `src/main.rs`.
+pub fn build_day_bin(config: &Config, year: u16, day: u8, files: &mut AutoFiles) -> Result {
+    let day_mod = format_ident!("day{}", day);
+    let day_fn = format_ident!("run_day{}", day);
+    let source_path = format!("../_{}/day{}.rs", year, day);
+    let settings = config.codegen();
+    let day_runner = codegen::day_runner(&settings, year, day)?;
+    let runner = codegen::runner_path(&settings);
+
+    let content = auto_file(quote!(
+        use qu::ick_use::*;
+        use std::time::Instant;
+
+        #[path = #source_path]
+        mod #day_mod;
+
+        #[qu::ick]
+        fn main(opt: #runner::Opt) {
+            #day_fn(&opt)?;
+            #runner::finish()
         }
-    ));
 
-    fs::write(year_folder.join("mod.rs"), content)?;
+        #day_runner
+    ))?;
 
+    files.add(config.day_bin(year, day), content);
     Ok(())
 }
 
 /// Create a file for the given year and day, only if it is not already present.
-pub fn build_day_src(config: &Config, year: u16, day: u8) -> Result {
+///
+/// The file is created from the named template in `.aoc/templates`. If no template is named, the
+/// `day` template is used if there is one, and the built-in template otherwise.
+pub fn build_day_src(
+    config: &Config,
+    year: u16,
+    day: u8,
+    template: Option<&str>,
+    report: &mut Report,
+) -> Result {
     let filename = config.day_source(year, day);
     if util::path_exists(&filename)? {
         log::info!(
@@ -127,53 +250,316 @@ pub fn build_day_src(config: &Config, year: u16, day: u8) -> Result {
             year,
             day
         );
+        report.file(filename, false);
+        return Ok(());
+    }
+
+    if let Some(template) = load_template(config, template)? {
+        let content = fill_template(config, &template, year, day)?;
+        fs::write(&filename, content)?;
+        report.file(filename, true);
         return Ok(());
     }
 
+    // Start from a parser that matches the input, if we have it.
+    let shape = match fs::read_to_string(config.input_path(year, day)).optional()? {
+        Some(input) if !input.is_empty() => input::analyse(&input),
+        _ => InputShape::Lines,
+    };
+    log::info!("Using {:?} skeleton for year {}, day {}", shape, year, day);
+    let (skeleton, parsed) = day_skeleton(shape);
+
     let content = gen_file(quote!(
         use qu::ick_use::*;
         use std::fmt;
 
-        // TODO rename and change fields to something useful
-        #[derive(Debug)]
-        pub struct MyType;
+        #skeleton
 
-        pub fn parse(_input: &str) -> Result<MyType> {
-            Ok(MyType)
-        }
-
-        pub fn part1(_input: &[MyType]) -> impl fmt::Display {
+        pub fn part1(_input: #parsed) -> impl fmt::Display {
             "<todo>"
         }
 
-        pub fn part2(_input: &[MyType]) -> impl fmt::Display {
+        pub fn part2(_input: #parsed) -> impl fmt::Display {
             "<todo>"
         }
-    ));
+    ))?;
 
     fs::write(&filename, content)?;
+    report.file(filename, true);
     Ok(())
 }
 
+/// The name of the template used when none is given.
+const DEFAULT_TEMPLATE: &str = "day";
+
 // Helpers
 // -------
 
-fn auto_file(content: impl fmt::Display) -> String {
-    const MSG: &str = "// NOTE: This file is auto-generated. `cargo-aoc` will overwrite any changes you make to it.\n\n";
-    if let Ok(mut fmt) = rustfmt(&content) {
-        fmt.insert_str(0, MSG);
-        fmt
-    } else {
-        format!("{}{}", MSG, content)
+/// A parser for the given shape of input, and the type the parts take.
+fn day_skeleton(shape: InputShape) -> (TokenStream, TokenStream) {
+    match shape {
+        InputShape::IntPerLine => (
+            quote!(
+                pub fn parse(input: &str) -> Result<i64> {
+                    Ok(input.parse()?)
+                }
+            ),
+            quote!(&[i64]),
+        ),
+        InputShape::CommaSeparated => (
+            quote!(
+                pub fn parse_input(input: &str) -> Result<Vec<i64>> {
+                    input
+                        .trim()
+                        .split(',')
+                        .map(|num| Ok(num.trim().parse()?))
+                        .collect()
+                }
+            ),
+            quote!(&[i64]),
+        ),
+        InputShape::Grid => (
+            quote!(
+                pub fn parse(input: &str) -> Result<Vec<u8>> {
+                    Ok(input.bytes().collect())
+                }
+            ),
+            quote!(&[Vec<u8>]),
+        ),
+        InputShape::Blocks => (
+            quote!(
+                pub fn parse_paragraph(input: &str) -> Result<Vec<String>> {
+                    Ok(input.lines().map(ToOwned::to_owned).collect())
+                }
+            ),
+            quote!(&[Vec<String>]),
+        ),
+        InputShape::Lines => (
+            quote!(
+                #[derive(Debug)]
+                pub struct Line;
+
+                pub fn parse(_input: &str) -> Result<Line> {
+                    Ok(Line)
+                }
+            ),
+            quote!(&[Line]),
+        ),
     }
 }
 
-fn gen_file(content: impl fmt::Display) -> String {
-    const MSG: &str = "\n\n// NOTE: This file was generated by `cargo-aoc`, and will not be overwritten. Feel free to delete this message.";
-    if let Ok(mut fmt) = rustfmt(&content) {
-        fmt.push_str(MSG);
-        fmt
-    } else {
-        format!("{}{}", content, MSG)
+/// Load a user-defined template, or the default one if there is one and no name is given.
+fn load_template(config: &Config, name: Option<&str>) -> Result<Option<String>> {
+    let path = config.template_path(name.unwrap_or(DEFAULT_TEMPLATE));
+    match fs::read_to_string(&path).optional()? {
+        Some(template) => Ok(Some(template)),
+        None => match name {
+            Some(name) => bail!("template {:?} not found at {}", name, path.display()),
+            None => Ok(None),
+        },
+    }
+}
+
+/// Replace the placeholders in a template.
+///
+/// The placeholders are `{{year}}`, `{{day}}`, `{{title}}` and `{{url}}`. The title is only known
+/// if the puzzle description has been downloaded, otherwise it is left empty.
+fn fill_template(config: &Config, template: &str, year: u16, day: u8) -> Result<String> {
+    let title = Puzzle::load(config, year, day)?
+        .and_then(|puzzle| puzzle.title)
+        .unwrap_or_default();
+    Ok(template
+        .replace("{{year}}", &year.to_string())
+        .replace("{{day}}", &day.to_string())
+        .replace("{{title}}", &title)
+        .replace("{{url}}", &web::puzzle_url(year, day)))
+}
+
+fn auto_file(content: TokenStream) -> Result<String> {
+    codegen::format(content)
+}
+
+/// The contents to write for a file from `auto_file`, with the contents of the user regions in
+/// the existing file.
+///
+/// The header records a hash of the body outside the user regions. If the existing file no longer
+/// matches its hash, it has been edited by hand, and is only overwritten with `--force`.
+fn fill_auto_file(config: &Config, path: &Path, content: &str) -> Result<String> {
+    const MSG: &str = "// NOTE: This file is auto-generated. `cargo-aoc` will overwrite any changes you make to it, \
+        except between `// aoc:user-begin` and `// aoc:user-end`.";
+    let existing = fs::read_to_string(path).optional()?;
+    let regions = match &existing {
+        Some(existing) => user_regions::read(existing, path)?,
+        None => Default::default(),
+    };
+    let body = user_regions::fill(content, regions, path)?;
+    let hash = content_hash(&body);
+    let content = format!("{}\n{}{}\n\n{}", MSG, HASH_PREFIX, hash, body);
+
+    let edited = existing
+        .as_deref()
+        .filter(|existing| *existing != content)
+        .and_then(edited_body);
+    if let Some(old_body) = edited {
+        if !config.force {
+            // Number the lines as they are in the file, header and all.
+            let existing = existing.as_deref().unwrap();
+            let header_lines = existing[..existing.len() - old_body.len()].lines().count();
+            let lost = util::removed_lines(old_body, &body)
+                .into_iter()
+                .map(|(line, text)| format!("{:>5} | {}\n", line + header_lines, text))
+                .collect::<String>();
+            bail!(
+                "{} has been edited outside of its user regions, and regenerating it would \
+                lose these lines:\n{}\
+                Move them between `// aoc:user-begin` and `// aoc:user-end`, \
+                or run again with `--force` to overwrite them",
+                path.display(),
+                lost
+            );
+        }
+        log::warn!("Overwriting changes made by hand to {}", path.display());
+    }
+    Ok(content)
+}
+
+const HASH_PREFIX: &str = "// aoc:hash ";
+
+/// The body of a generated file, if it no longer matches the hash in its header.
+///
+/// Files without a hash, such as ones from before hashes were added, can't be checked.
+fn edited_body(content: &str) -> Option<&str> {
+    split_header(content)
+        .and_then(|(recorded, body)| (recorded != content_hash(body)).then_some(body))
+}
+
+/// Split a file from `fill_auto_file` into the hash recorded in its header, and its body. `None`
+/// if it has no hash.
+fn split_header(content: &str) -> Option<(&str, &str)> {
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        offset += line.len();
+        if let Some(hash) = line.strip_prefix(HASH_PREFIX) {
+            let body = &content[offset..];
+            return Some((hash.trim(), body.strip_prefix('\n').unwrap_or(body)));
+        }
+    }
+    None
+}
+
+/// Hash the body of a generated file, apart from the user regions.
+///
+/// This is 64-bit FNV-1a, which is plenty to spot changes, and doesn't need a dependency.
+fn content_hash(body: &str) -> String {
+    let hash = user_regions::strip(body)
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!("{:016x}", hash)
+}
+
+fn gen_file(content: TokenStream) -> Result<String> {
+    Ok(gen_text(&codegen::format(content)?))
+}
+
+/// Add the note for generated files to code that is already formatted.
+fn gen_text(content: &str) -> String {
+    const MSG: &str = "\n\n// NOTE: This file was generated by `cargo-aoc`, and will not be overwritten. Feel free to delete this message.\n";
+    format!("{}{}", content.trim_end(), MSG)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AocConfig;
+
+    const GENERATED: &str = "fn main() {\n    __aoc_user_region!(main);\n    run();\n}\n";
+
+    /// A project in a new temporary folder, and the path of a generated file in it.
+    fn project(name: &str, force: bool) -> (Config, PathBuf) {
+        let project_root = std::env::temp_dir().join(format!(
+            "cargo-aoc-file-gen-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&project_root);
+        fs::create_dir_all(project_root.join("src")).unwrap();
+        let path = project_root.join("src/main.rs");
+        let config = Config {
+            config_path: project_root.join(codegen::CONFIG_PATH),
+            project_root,
+            file: AocConfig::default(),
+            force,
+        };
+        (config, path)
+    }
+
+    #[test]
+    fn hash_round_trip() {
+        let (config, path) = project("round-trip", false);
+        let content = fill_auto_file(&config, &path, GENERATED).unwrap();
+        let (hash, body) = split_header(&content).unwrap();
+        assert_eq!(hash, content_hash(body));
+        assert!(body.starts_with("fn main() {\n"));
+        assert_eq!(edited_body(&content), None);
+
+        // Changes in user regions are kept, and aren't edits.
+        let content = content.replace("    // aoc:user-end", "    let x = 1;\n    // aoc:user-end");
+        fs::write(&path, &content).unwrap();
+        assert_eq!(edited_body(&content), None);
+        assert_eq!(fill_auto_file(&config, &path, GENERATED).unwrap(), content);
+    }
+
+    #[test]
+    fn edited_body_is_refused() {
+        let (config, path) = project("edited", false);
+        let content = fill_auto_file(&config, &path, GENERATED).unwrap();
+        fs::write(&path, content.replace("run();", "run(1);")).unwrap();
+
+        let error = fill_auto_file(&config, &path, GENERATED)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("would lose these lines:\n    7 |     run(1);\nMove them"),
+            "{}",
+            error
+        );
+
+        let (config, _) = project("edited", true);
+        assert_eq!(fill_auto_file(&config, &path, GENERATED).unwrap(), content);
+    }
+
+    #[test]
+    fn header_only_change() {
+        let (config, path) = project("header", false);
+        let content = fill_auto_file(&config, &path, GENERATED).unwrap();
+        let (_, without_note) = content.split_once('\n').unwrap();
+        fs::write(&path, without_note).unwrap();
+        assert_eq!(edited_body(without_note), None);
+        assert_eq!(fill_auto_file(&config, &path, GENERATED).unwrap(), content);
+    }
+
+    #[test]
+    fn no_header() {
+        let (config, path) = project("no-header", false);
+        fs::write(&path, "fn main() {\n    old();\n}\n").unwrap();
+        assert_eq!(split_header("fn main() {\n    old();\n}\n"), None);
+        let content = fill_auto_file(&config, &path, GENERATED).unwrap();
+        assert!(content.ends_with("    run();\n}\n"));
+    }
+
+    #[test]
+    fn generated_files_end_with_a_newline() {
+        let content = gen_file(quote!(
+            pub struct Line;
+            pub fn parse(_input: &str) -> Line {
+                Line
+            }
+        ))
+        .unwrap();
+        assert!(content.starts_with("pub struct Line;\n\npub fn parse("));
+        assert!(content.ends_with("Feel free to delete this message.\n"));
+        assert!(!content.ends_with("\n\n"));
     }
 }
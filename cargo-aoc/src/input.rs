@@ -1,157 +1,103 @@
 //! Functionality around fetching and storing puzzle input.
 
 use crate::{util::path_exists, Config};
-use qu::ick_use::*;
-use regex::Regex;
-use std::{
-    fs, io,
-    ops::{Bound, RangeBounds},
-};
+use std::{fs, io};
 
-pub fn missing_input(
-    config: &Config,
-    year: u16,
-    days: impl RangeBounds<u8>,
-) -> impl Iterator<Item = Result<(u16, u8)>> + '_ {
-    let days_start = match days.start_bound() {
-        Bound::Unbounded => 0,
-        Bound::Excluded(bound) => (*bound + 1),
-        Bound::Included(bound) => (*bound),
-    }
-    .max(1);
-    let days_end = match days.end_bound() {
-        Bound::Unbounded => u8::MAX,
-        Bound::Excluded(bound) => (*bound - 1),
-        Bound::Included(bound) => (*bound),
-    }
-    .min(25);
-    MissingInput::new(config, year, days_start, days_end)
-}
-
-struct MissingInput<'a> {
-    config: &'a Config,
-    year: u16,
-    days_current: u8,
-    days_end: u8,
-}
-
-impl<'a> MissingInput<'a> {
-    fn new(config: &'a Config, year: u16, days_start: u8, days_end: u8) -> Self {
-        Self {
-            config,
-            year,
-            days_current: days_start,
-            days_end,
-        }
+/// Create an empty input file for the given day, if there isn't one already.
+///
+/// This lets the generated code compile before the input is downloaded. Returns `true` if the
+/// file was created.
+pub fn save_placeholder(config: &Config, year: u16, day: u8) -> io::Result<bool> {
+    let path = config.input_path(year, day);
+    fs::create_dir_all(path.parent().unwrap())?;
+    if path_exists(&path)? {
+        return Ok(false);
     }
+    fs::write(&path, "")?;
+    Ok(true)
 }
 
-impl<'a> Iterator for MissingInput<'a> {
-    type Item = Result<(u16, u8)>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        fn inner(this: &mut MissingInput<'_>) -> Result<Option<(u16, u8)>> {
-            while input_exists(this.config, this.year, this.days_current)? {
-                log::info!(
-                    "Skipping already-present input for year {}, day {}",
-                    this.year,
-                    this.days_current
-                );
-                this.days_current += 1;
-            }
-            Ok(if this.days_current > this.days_end {
-                None
-            } else {
-                let ans = Some((this.year, this.days_current));
-                this.days_current += 1;
-                ans
-            })
-        }
-        inner(self).transpose()
-    }
-}
+/// Returns `true` if there was already a file there (that file was overwritten)
+pub fn save_input(config: &Config, year: u16, day: u8, input: impl AsRef<str>) -> io::Result<bool> {
+    let path = config.input_path(year, day);
+    fs::create_dir_all(path.parent().unwrap())?;
+    // Possible race condition here (file could be deleted in between) but I don't think it matters.
+    // Just making a note.
+    let exists = path_exists(&path)?;
 
-/// Gets an iterator over all days in a given year where there is input
-pub fn all_for_year(config: &Config, year: u16) -> impl Iterator<Item = Result<u8>> + '_ {
-    AllForYear::new(config, year)
+    fs::write(&path, input.as_ref())?;
+    Ok(exists)
 }
 
-struct AllForYear<'a> {
-    config: &'a Config,
-    year: u16,
-    days_current: u8,
+/// The overall shape of a puzzle input, used to pick a starting point for parsing it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InputShape {
+    /// One integer per line.
+    IntPerLine,
+    /// Integers separated by commas.
+    CommaSeparated,
+    /// A grid of characters, with every line the same length.
+    Grid,
+    /// Groups of lines separated by blank lines.
+    Blocks,
+    /// Anything else, most likely lines with some structure to them.
+    Lines,
 }
 
-impl<'a> AllForYear<'a> {
-    fn new(config: &'a Config, year: u16) -> Self {
-        Self {
-            config,
-            year,
-            days_current: 1,
-        }
+/// Guess the shape of a puzzle input.
+pub fn analyse(input: &str) -> InputShape {
+    let input = input.trim_end();
+    if input.contains("\n\n") {
+        return InputShape::Blocks;
+    }
+    let lines = input.lines().collect::<Vec<_>>();
+    let is_int = |s: &str| s.trim().parse::<i64>().is_ok();
+    let same_width = lines.len() > 1
+        && lines[0].len() > 1
+        && lines.iter().all(|line| line.len() == lines[0].len());
+    // Lines of digits that are all the same width are a grid, e.g. of heights or bits. A list of
+    // integers has a sign or a width that varies somewhere.
+    let digit_grid = same_width && input.bytes().all(|b| b.is_ascii_digit() || b == b'\n');
+    if !lines.is_empty() && !digit_grid && lines.iter().all(|line| is_int(line)) {
+        InputShape::IntPerLine
+    } else if same_width && !input.contains(|ch: char| ch.is_whitespace() && ch != '\n') {
+        InputShape::Grid
+    } else if input.contains(',') && input.lines().flat_map(|line| line.split(',')).all(is_int) {
+        InputShape::CommaSeparated
+    } else {
+        InputShape::Lines
     }
 }
 
-impl<'a> Iterator for AllForYear<'a> {
-    type Item = Result<u8>;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        fn inner(this: &mut AllForYear<'_>) -> Result<Option<u8>> {
-            if this.days_current > 25 {
-                return Ok(None);
-            }
-            while !input_exists(this.config, this.year, this.days_current)? {
-                log::warn!(
-                    "Skipping missing input for year {}, day {}",
-                    this.year,
-                    this.days_current
-                );
-                this.days_current += 1;
-                if this.days_current > 25 {
-                    return Ok(None);
-                }
-            }
-            let ans = this.days_current;
-            this.days_current += 1;
-            Ok(Some(ans))
+    #[test]
+    fn shapes() {
+        let cases = [
+            ("199\n200\n1208\n210\n", InputShape::IntPerLine),
+            ("1\n-20\n300\n", InputShape::IntPerLine),
+            ("-10\n+20\n-30\n", InputShape::IntPerLine),
+            ("1\n2\n3\n", InputShape::IntPerLine),
+            ("3,4,3,1,2\n", InputShape::CommaSeparated),
+            ("..#.\n#..#\n.##.\n", InputShape::Grid),
+            ("00100\n11110\n10110\n", InputShape::Grid),
+            ("1000\n2000\n\n4000\n", InputShape::Blocks),
+            ("forward 5\ndown 5\nup 3\n", InputShape::Lines),
+            ("abc\nde\n", InputShape::Lines),
+        ];
+        for (input, shape) in cases {
+            assert_eq!(analyse(input), shape, "{:?}", input);
         }
-        inner(self).transpose()
     }
-}
 
-pub fn get_years(config: &Config) -> Result<impl Iterator<Item = u16>> {
-    let mut years_remaining = vec![];
-    let regex = Regex::new(r"_(\d+)$").unwrap();
-    for entry in fs::read_dir(config.project_root.join("src"))? {
-        let entry = entry?;
-        if entry.metadata()?.is_dir() {
-            if let Some(caps) = regex.captures(entry.path().to_str().context("non-utf8 path")?) {
-                years_remaining.push(
-                    caps.get(1)
-                        .unwrap()
-                        .as_str()
-                        .parse::<u16>()
-                        .context("cannot parse year from folder name")?,
-                );
-            }
+    #[test]
+    fn digit_grids() {
+        // Rows of digits are a grid whether or not they would fit in an `i64`.
+        for width in [10, 100] {
+            let input = format!("{}\n", "5483143223".repeat(width / 10)).repeat(10);
+            assert_eq!(analyse(&input), InputShape::Grid, "{:?}", input);
         }
     }
-    Ok(years_remaining.into_iter())
-}
-
-pub fn input_exists(config: &Config, year: u16, day: u8) -> io::Result<bool> {
-    let path = config.input_path(year, day);
-    path_exists(&path)
-}
-
-/// Returns `true` if there was already a file there (that file was overwritten)
-pub fn save_input(config: &Config, year: u16, day: u8, input: impl AsRef<str>) -> io::Result<bool> {
-    let path = config.input_path(year, day);
-    fs::create_dir_all(path.parent().unwrap())?;
-    // Possible race condition here (file could be deleted in between) but I don't think it matters.
-    // Just making a note.
-    let exists = path_exists(&path)?;
-
-    fs::write(&path, input.as_ref())?;
-    Ok(exists)
 }
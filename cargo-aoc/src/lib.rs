@@ -0,0 +1,28 @@
+//! The library behind `cargo-aoc`, for tools that manage Advent of Code projects without running
+//! the command.
+//!
+//! - [`Config`] finds a project and its settings, and [`Project`] is what it has for each day.
+//! - [`input`] stores puzzle input, which [`web`] downloads along with [`puzzle`] descriptions
+//!   and the [`stars`] for each day.
+//! - [`ops`] does what the commands do, e.g. downloading a year or generating the code that runs
+//!   the solutions, and [`compile`] builds and runs them.
+//! - [`Report`] records what they did, e.g. the files they wrote.
+//!
+//! Everything that can fail returns an [`Error`] that says what failed.
+
+pub mod compile;
+pub mod config;
+mod error;
+mod file_gen;
+pub mod input;
+pub mod ops;
+pub mod project;
+pub mod puzzle;
+pub mod report;
+pub mod stars;
+mod user_regions;
+mod util;
+pub mod watch;
+pub mod web;
+
+pub use crate::{config::Config, error::Error, project::Project, report::Report};
@@ -1,19 +1,30 @@
-use cargo_toml::{Dependency, DepsSet, Manifest};
+use cargo_aoc::{
+    compile::{self, Selection},
+    config::{Config, InputMode, Layout},
+    input, ops,
+    report::{ErrorReport, Report},
+    watch, Project,
+};
+use log::LevelFilter;
 use qu::ick_use::*;
-use std::{env, fs, ops::RangeBounds, path::PathBuf};
-
-mod config;
-mod file_gen;
-mod input;
-mod util;
-mod web;
-
-use crate::{config::Config, util::IoResultExt};
+use std::{env, path::PathBuf, process};
+use structopt::clap::ErrorKind;
 
 #[derive(StructOpt)]
 struct Opt {
     #[structopt(long, short, parse(from_os_str))]
     working_dir: Option<PathBuf>,
+    /// Overwrite generated files even if they have been edited by hand.
+    ///
+    /// Without this, `cargo-aoc` stops and shows what would be lost instead.
+    #[structopt(long, short)]
+    force: bool,
+    /// Print a JSON report of what the command did, instead of logging.
+    ///
+    /// The report lists the days downloaded, the files generated and skipped, and what stopped the
+    /// command if it failed. Doesn't work with `run` and `watch`.
+    #[structopt(long)]
+    json: bool,
     #[structopt(subcommand)]
     cmd: Cmd,
 }
@@ -24,7 +35,27 @@ enum Cmd {
     ///
     /// This creates a folder with the required structure. The folder will be called
     /// `aoc` and will be in the current directory.
-    New,
+    New {
+        /// How the generated code gets hold of puzzle input, either `embed` or `runtime`.
+        ///
+        /// `embed` compiles the input into the binary, `runtime` reads it from the project folder
+        /// when the solutions are run. This can be changed later in `.aoc.toml`.
+        #[structopt(long, default_value = "embed")]
+        input_mode: InputMode,
+        /// How the generated code is laid out, either `modules` or `bins`.
+        ///
+        /// `modules` compiles all the solutions into one binary. `bins` also builds a binary for
+        /// each day (e.g. `2022_day01`), so days compile independently, sharing a library crate
+        /// for helpers.
+        #[structopt(long, default_value = "modules")]
+        layout: Layout,
+        /// Generate the code that runs the solutions from a build script, on every build.
+        ///
+        /// New day files are then picked up without running `cargo-aoc`, and the generated code
+        /// is never checked in. Only works with the `modules` layout.
+        #[structopt(long)]
+        build_script: bool,
+    },
     /// Set the cookie used to download puzzle input
     SetCookie { cookie: String },
     /// Shows the cookie used to download puzzle input
@@ -33,36 +64,204 @@ enum Cmd {
     ///
     /// The year defaults to the latest one there is data for.
     Download { year: Option<u16> },
+    /// Create the source file for a day, without downloading anything.
+    ///
+    /// An empty input file is created if there is no input yet, so the solutions still compile.
+    /// The day is reported as having no input when it is run, until the input is downloaded.
+    Gen {
+        year: u16,
+        day: u8,
+        /// Create the source file from `.aoc/templates/<template>.rs`.
+        ///
+        /// Defaults to `.aoc/templates/day.rs` if it exists. In templates, `{{year}}`, `{{day}}`,
+        /// `{{title}}` and `{{url}}` are replaced with details of the puzzle.
+        #[structopt(long, short)]
+        template: Option<String>,
+    },
+    /// Get everything ready for today's puzzle.
+    ///
+    /// Downloads the input, puzzle description and example, generates the source file for the day,
+    /// and prints where it is. Only works during advent, using the puzzle release time (midnight
+    /// US Eastern time).
+    Today,
+    /// Show a calendar of the whole project.
+    ///
+    /// For each year and day, shows whether there is input (`#`), a source file (`#`), and which
+    /// parts have been solved (`*`), going by the answers in the saved puzzle descriptions and
+    /// the stars from the last `sync`.
+    Status,
+    /// Fetch the stars the website shows for each day, so `status` knows about every part solved.
+    ///
+    /// Without a year, every year with any stars is synced. The stars are kept in
+    /// `.aoc/stars.toml`.
+    Sync { year: Option<u16> },
+    /// Build and run the solutions.
+    ///
+    /// Generated files are brought up to date first, so new day files are picked up.
+    Run {
+        /// Only build and run the solutions for this year.
+        ///
+        /// Only the cargo feature for this year is enabled, so other years are not compiled.
+        #[structopt(long, short)]
+        year: Option<u16>,
+        /// Only run the solutions for this day.
+        #[structopt(long, short)]
+        day: Option<u8>,
+        /// Only run this part, either `1` or `2`.
+        #[structopt(long, short)]
+        part: Option<u8>,
+        /// Build the solutions with optimizations.
+        #[structopt(long, short)]
+        release: bool,
+    },
+    /// Re-run a day every time its source, input or examples change.
+    ///
+    /// The day is run against its example input first (`input/<year>/example<day>.txt`), if there
+    /// is one, then against the real input. Changes to shared helpers in `src` also trigger a
+    /// re-run.
+    Watch {
+        /// The year of the day to watch.
+        ///
+        /// Defaults to the latest year.
+        #[structopt(long, short)]
+        year: Option<u16>,
+        /// The day to watch.
+        #[structopt(long, short)]
+        day: u8,
+        /// Only run this part, either `1` or `2`.
+        #[structopt(long, short)]
+        part: Option<u8>,
+        /// Build the solutions with optimizations.
+        #[structopt(long, short)]
+        release: bool,
+    },
     /// For development
     Test,
 }
 
-#[qu::ick]
-fn main(opt: Opt) -> Result {
+/// The command line, with the options for how much to log.
+#[derive(StructOpt)]
+struct Args {
+    #[structopt(flatten)]
+    opt: Opt,
+    #[structopt(short, long, parse(from_occurrences))]
+    quiet: i8,
+    #[structopt(short, long, parse(from_occurrences))]
+    verbose: i8,
+}
+
+/// Exits with a code that says what failed, see `cargo_aoc::Error::exit_code`. That includes
+/// arguments that can't be parsed, which are an [`Error::Usage`](cargo_aoc::Error::Usage).
+fn main() {
+    let args = Args::from_iter_safe(env::args_os()).unwrap_or_else(|e| match e.kind {
+        // `--help` and `--version` stop here too, but they aren't errors.
+        ErrorKind::HelpDisplayed | ErrorKind::VersionDisplayed => e.exit(),
+        _ => {
+            eprintln!("{}", e.message);
+            process::exit(cargo_aoc::Error::Usage(e.into()).exit_code());
+        }
+    });
+    let log_level = match 3i8.saturating_add(args.verbose).saturating_sub(args.quiet) {
+        i8::MIN..=0 => LevelFilter::Off,
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+    env_logger::builder().filter_level(log_level).init();
+    if let Err(e) = try_main(args.opt) {
+        log::error!("{:?}", e);
+        process::exit(1);
+    }
+}
+
+fn try_main(opt: Opt) -> Result {
+    let json = opt.json;
+    if json {
+        log::set_max_level(LevelFilter::Off);
+    }
+    let mut report = Report::default();
+    let result = dispatch(opt, &mut report);
+    if let Err(e) = &result {
+        if json {
+            report.errors.push(ErrorReport::new(e));
+        } else {
+            log::error!("{:?}", e);
+        }
+    }
+    if json {
+        println!("{}", serde_json::to_string(&report)?);
+    }
+    if let Err(e) = result {
+        process::exit(ErrorReport::new(&e).exit_code);
+    }
+    Ok(())
+}
+
+fn dispatch(opt: Opt, report: &mut Report) -> Result {
     if let Some(dir) = &opt.working_dir {
-        env::set_current_dir(dir)?;
+        env::set_current_dir(dir)
+            .with_context(|| format!("cannot change to {}", dir.display()))
+            .map_err(cargo_aoc::Error::Usage)?;
+    }
+    if opt.json && matches!(opt.cmd, Cmd::Run { .. } | Cmd::Watch { .. }) {
+        return Err(cargo_aoc::Error::Usage(format_err!(
+            "`--json` only works with commands that manage the project, `run` and `watch` print \
+            the results of the solutions instead"
+        ))
+        .into());
     }
     match opt.cmd {
-        Cmd::New => new(),
+        Cmd::New {
+            input_mode,
+            layout,
+            build_script,
+        } => new(input_mode, layout, build_script, report),
         Cmd::SetCookie { cookie } => set_cookie(cookie),
-        Cmd::ShowCookie => show_cookie(),
-        Cmd::Download { year } => download(year, ..),
+        Cmd::ShowCookie => show_cookie(report),
+        Cmd::Download { year } => download(year, opt.force, report),
+        Cmd::Gen {
+            year,
+            day,
+            template,
+        } => gen(year, day, template.as_deref(), opt.force, report),
+        Cmd::Today => today(opt.force, report),
+        Cmd::Status => status(opt.json, report),
+        Cmd::Sync { year } => sync(year, report),
+        Cmd::Run {
+            year,
+            day,
+            part,
+            release,
+        } => run(
+            Selection {
+                year,
+                day,
+                part,
+                input: None,
+            },
+            release,
+            opt.force,
+        ),
+        Cmd::Watch {
+            year,
+            day,
+            part,
+            release,
+        } => watch(
+            year.unwrap_or_else(ops::current_year),
+            day,
+            part,
+            release,
+            opt.force,
+        ),
         Cmd::Test => test(),
     }
 }
 
-fn new() -> Result {
-    let config = Config::create_project()?;
-    // Add dependencies
-    let cargo_toml_path = config.project_root.join("Cargo.toml");
-    let mut manifest = Manifest::from_path(&cargo_toml_path)?;
-    manifest.bin.clear();
-    ensure_dep("structopt", &mut manifest.dependencies);
-    ensure_dep("qu", &mut manifest.dependencies);
-    fs::write(cargo_toml_path, toml::to_vec(&manifest)?)?;
-    // Write files
-    file_gen::build_main_rs(&config)?;
-    config.save()?;
+fn new(input_mode: InputMode, layout: Layout, build_script: bool, report: &mut Report) -> Result {
+    ops::new_project(input_mode, layout, build_script, report)?;
     Ok(())
 }
 
@@ -74,73 +273,129 @@ fn set_cookie(cookie: String) -> Result {
     Ok(())
 }
 
-fn show_cookie() -> Result {
+fn show_cookie(report: &mut Report) -> Result {
     let config = Config::load().context("cannot load aoc config")?;
     match &config.file.cookie {
         Some(cookie) => log::info!("cookie: {:?}", cookie),
         None => log::info!("cookie: <unset>"),
     }
+    report.cookie = config.file.cookie.clone();
     Ok(())
 }
 
 /// The download command.
 ///
 /// Defaults to most recent year, and all available days.
-fn download(year: Option<u16>, days: impl RangeBounds<u8>) -> Result {
-    let config = Config::load().context("cannot load aoc config")?;
-    let cookie = match config.file.cookie.as_ref() {
-        Some(cookie) => cookie.as_str(),
-        None => {
-            return Err(format_err!(
-                "you need to store your cookie before you can download input"
-            ))
-        }
-    };
+fn download(year: Option<u16>, force: bool, report: &mut Report) -> Result {
+    let mut config = Config::load().context("cannot load aoc config")?;
+    config.force = force;
+    ops::download(&config, year.unwrap_or_else(ops::current_year), report)?;
+    Ok(())
+}
 
-    let year = year.unwrap_or(current_year());
+/// The gen command.
+fn gen(year: u16, day: u8, template: Option<&str>, force: bool, report: &mut Report) -> Result {
+    let mut config = Config::load().context("cannot load aoc config")?;
+    config.force = force;
+    ops::gen(&config, year, day, template, report)?;
+    Ok(())
+}
 
-    fs::create_dir_all(config.year_folder(year))?;
-    for res in input::missing_input(&config, year, days) {
-        let (year, day) = res?;
-        let content = web::get_day(cookie, year, day)?;
-        input::save_input(&config, year, day, content)?;
-    }
-    for res in input::all_for_year(&config, year) {
-        let day = res?;
-        log::info!("Generating source file for year {}, day {}", year, day);
-        file_gen::build_day_src(&config, year, day)?;
+/// The today command.
+fn today(force: bool, report: &mut Report) -> Result {
+    let mut config = Config::load().context("cannot load aoc config")?;
+    config.force = force;
+    ops::today(&config, report)?;
+    Ok(())
+}
+
+/// The status command.
+fn status(json: bool, report: &mut Report) -> Result {
+    let config = Config::load().context("cannot load aoc config")?;
+    let project = ops::status(&config, report)?;
+    if !json {
+        print!("{}", project.calendar());
     }
-    log::info!("Generating mod file for year {}", year);
-    file_gen::build_mod_file(&config, year)?;
-    log::info!("Generating main.rs");
-    file_gen::build_main_rs(&config)?;
     Ok(())
 }
 
-fn test() -> Result {
-    let _config = Config::load()?;
+/// The sync command.
+fn sync(year: Option<u16>, report: &mut Report) -> Result {
+    let config = Config::load().context("cannot load aoc config")?;
+    ops::sync(&config, year, report)?;
     Ok(())
 }
 
-// If we're into december, then the current year, else the previous one.
-fn current_year() -> u16 {
-    use chrono::Datelike;
+/// The run command.
+///
+/// Builds the generated project, only compiling the selected year if there is one, then runs it.
+fn run(selection: Selection, release: bool, force: bool) -> Result {
+    let mut config = Config::load().context("cannot load aoc config")?;
+    config.force = force;
+    ops::regenerate(&config, &mut Report::default())?;
+    let executable = compile::build(&config, selection.year, release)?;
+    if let (Some(year), Some(day)) = (selection.year, selection.day) {
+        log_solved(&Project::scan(&config)?, year, day);
+    }
+    compile::run(&config, &executable, &selection)?;
+    Ok(())
+}
 
-    let now = chrono::Utc::now();
-    let year = now.year().try_into().expect("integer conversion");
-    if now.month() == 12 {
-        year
-    } else {
-        year - 1
+/// The watch command.
+///
+/// Rebuilds and re-runs the given day whenever one of its files changes. Never returns unless there
+/// is an error watching the files.
+fn watch(year: u16, day: u8, part: Option<u8>, release: bool, force: bool) -> Result {
+    let mut config = Config::load().context("cannot load aoc config")?;
+    config.force = force;
+    // Created now, so the watcher doesn't see it appear and run the day again straight away.
+    input::save_placeholder(&config, year, day)?;
+    let mut watcher = watch::Watcher::new(&config, year, day);
+    loop {
+        watch::clear_screen();
+        log::info!("Watching year {}, day {} (press Ctrl-C to stop)", year, day);
+        if let Err(e) = run_watched(&config, year, day, part, release) {
+            log::error!("{:#}", e);
+        }
+        watcher.wait_for_change()?;
     }
 }
 
-fn ensure_dep(name: &str, set: &mut DepsSet) {
-    if !contains_dep(name, set) {
-        set.insert(name.into(), Dependency::Simple("*".into()));
+/// Build the given day, then run it against the example input if there is one, and the real input.
+fn run_watched(config: &Config, year: u16, day: u8, part: Option<u8>, release: bool) -> Result {
+    ops::regenerate(config, &mut Report::default())?;
+    let executable = compile::build(config, Some(year), release)?;
+    let mut selection = Selection {
+        year: Some(year),
+        day: Some(day),
+        part,
+        input: None,
+    };
+    let project = Project::scan(config)?;
+    log_solved(&project, year, day);
+    if project.day(year, day).example {
+        log::info!("Example input:");
+        selection.input = Some(config.example_path(year, day));
+        compile::run(config, &executable, &selection)?;
+        selection.input = None;
     }
+    log::info!("Real input:");
+    compile::run(config, &executable, &selection)?;
+    Ok(())
 }
 
-fn contains_dep(name: &str, set: &DepsSet) -> bool {
-    set.iter().any(|(dep_name, _)| dep_name == name)
+/// Log which parts of the day are already solved, with the accepted answers where we know them.
+fn log_solved(project: &Project, year: u16, day: u8) {
+    let info = project.day(year, day);
+    for part in 1..=info.stars() {
+        match info.answers.get(part - 1) {
+            Some(answer) => log::info!("Part {} is solved, the answer was {}", part, answer),
+            None => log::info!("Part {} is solved", part),
+        }
+    }
+}
+
+fn test() -> Result {
+    let _config = Config::load()?;
+    Ok(())
 }
@@ -0,0 +1,444 @@
+//! What the `cargo-aoc` commands do to a project, put together from the rest of the library.
+
+use crate::{
+    config::{Config, InputMode, Layout},
+    file_gen::{self, AutoFiles},
+    input,
+    project::Project,
+    puzzle::{self, Puzzle},
+    report::{self, Report},
+    stars::{self, Stars},
+    util, web, Error,
+};
+use cargo_aoc_codegen as codegen;
+use qu::ick_use::*;
+use std::{collections::BTreeMap, fs};
+use toml_edit::{table, value, Array, Document, InlineTable, Item, TableLike, Value};
+
+/// Create a new project in a folder called `aoc` in the current directory.
+///
+/// With `build_script`, the code that runs the solutions is generated by the project's build
+/// script, which only works with the modules layout.
+pub fn new_project(
+    input_mode: InputMode,
+    layout: Layout,
+    build_script: bool,
+    report: &mut Report,
+) -> Result<(), Error> {
+    if build_script && layout != Layout::Modules {
+        return Err(Error::Usage(format_err!(
+            "`--build-script` only works with the `modules` layout"
+        )));
+    }
+    let mut config = Config::create_project()?;
+    config.file.input_mode = input_mode;
+    config.file.layout = layout;
+    config.file.build_script = build_script;
+    report.project = Some(config.project_root.clone());
+    let project = Project::scan(&config)?;
+    update_manifest(&config, &project, report).map_err(Error::Codegen)?;
+    // Write files
+    if build_script {
+        file_gen::build_build_script(&config, report).map_err(Error::Codegen)?;
+    } else {
+        let mut files = AutoFiles::default();
+        if layout == Layout::Bins {
+            file_gen::build_lib_rs(&config, report).map_err(Error::Codegen)?;
+            file_gen::build_runner_rs(&config, &project, &mut files).map_err(Error::Codegen)?;
+        }
+        file_gen::build_main_rs(&config, &project, &mut files).map_err(Error::Codegen)?;
+        files.write(&config, report).map_err(Error::Codegen)?;
+    }
+    config.save()?;
+    Ok(())
+}
+
+/// Download all the input for the given year that isn't there already, and create source files
+/// for the days that have input.
+///
+/// During advent, days after today aren't released yet, so they are left for later.
+pub fn download(config: &Config, year: u16, report: &mut Report) -> Result<(), Error> {
+    let cookie = config.cookie()?;
+    let mut project = Project::scan(config)?;
+    let last_day = match todays_puzzle() {
+        Ok((today_year, today)) if today_year == year => today,
+        _ => 25,
+    };
+
+    fs::create_dir_all(config.year_folder(year))?;
+    for day in 1..=last_day {
+        if project.day(year, day).input {
+            log::info!(
+                "Skipping already-present input for year {}, day {}",
+                year,
+                day
+            );
+            continue;
+        }
+        let content = match web::get_day(cookie, year, day) {
+            Ok(content) => content,
+            // Our clock may disagree with the website's, and later days are locked too.
+            Err(Error::NotReleased(_)) => {
+                log::info!("Year {}, day {} is not released yet", year, day);
+                break;
+            }
+            Err(e) => return Err(e),
+        };
+        input::save_input(config, year, day, content)?;
+        report.downloaded.push(report::Day { year, day });
+        report.file(config.input_path(year, day), true);
+        project.day_mut(year, day).input = true;
+    }
+    // The input is worth keeping either way, but new source files are not.
+    check_hand_edits(config)?;
+    for (day, _) in project.days(year).filter(|(_, day)| day.input) {
+        log::info!("Generating source file for year {}, day {}", year, day);
+        file_gen::build_day_src(config, year, day, None, report).map_err(Error::Codegen)?;
+    }
+    regenerate(config, report)
+}
+
+/// Create the source file for a day, without downloading anything.
+///
+/// An empty input file is created if there is no input yet, so the solutions still compile. The
+/// source file is created from the named template, if there is one.
+pub fn gen(
+    config: &Config,
+    year: u16,
+    day: u8,
+    template: Option<&str>,
+    report: &mut Report,
+) -> Result<(), Error> {
+    if !(1..=25).contains(&day) {
+        return Err(Error::Usage(format_err!(
+            "day must be between 1 and 25, found {}",
+            day
+        )));
+    }
+    report.day = Some(report::Day { year, day });
+    check_hand_edits(config)?;
+    fs::create_dir_all(config.year_folder(year))?;
+    let created = input::save_placeholder(config, year, day)?;
+    report.file(config.input_path(year, day), created);
+    log::info!("Generating source file for year {}, day {}", year, day);
+    file_gen::build_day_src(config, year, day, template, report).map_err(Error::Codegen)?;
+    regenerate(config, report)?;
+    log::info!("Source: {}", config.day_source(year, day).display());
+    report.source = Some(config.day_source(year, day));
+    Ok(())
+}
+
+/// Get everything ready for today's puzzle: the input, puzzle description, example and source
+/// file.
+pub fn today(config: &Config, report: &mut Report) -> Result<(), Error> {
+    let cookie = config.cookie()?;
+    let (year, day) = todays_puzzle()?;
+    log::info!("Getting ready for year {}, day {}", year, day);
+    report.day = Some(report::Day { year, day });
+
+    if Project::scan(config)?.day(year, day).input {
+        report.file(config.input_path(year, day), false);
+    } else {
+        let content = web::get_day(cookie, year, day)?;
+        input::save_input(config, year, day, content)?;
+        report.downloaded.push(report::Day { year, day });
+        report.file(config.input_path(year, day), true);
+    }
+    let page = web::get_puzzle(cookie, year, day)?;
+    let puzzle = Puzzle::parse(&page);
+    puzzle::save(config, year, day, &puzzle, report)?;
+
+    check_hand_edits(config)?;
+    fs::create_dir_all(config.year_folder(year))?;
+    file_gen::build_day_src(config, year, day, None, report).map_err(Error::Codegen)?;
+    regenerate(config, report)?;
+
+    if let Some(title) = &puzzle.title {
+        log::info!("Day {}: {}", day, title);
+    }
+    log::info!("Source: {}", config.day_source(year, day).display());
+    log::info!("Puzzle: {}", web::puzzle_url(year, day));
+    report.title = puzzle.title;
+    report.source = Some(config.day_source(year, day));
+    report.url = Some(web::puzzle_url(year, day));
+    Ok(())
+}
+
+/// Find out what the project has for each day, for a calendar of the whole project.
+pub fn status(config: &Config, report: &mut Report) -> Result<Project, Error> {
+    let project = Project::scan(config)?;
+    for year in project.years() {
+        for (day, status) in project.days(year) {
+            report.days.push(report::DayStatus {
+                year,
+                day,
+                input: status.input,
+                source: status.source,
+                example: status.example,
+                stars: status.stars(),
+            });
+        }
+    }
+    Ok(project)
+}
+
+/// Fetch the stars the website shows for each day, and keep them in `.aoc/stars.toml`.
+///
+/// Without a year, the events page says which years have any stars, and only their calendars are
+/// fetched.
+pub fn sync(config: &Config, year: Option<u16>, report: &mut Report) -> Result<(), Error> {
+    let cookie = config.cookie()?;
+    let mut synced = Stars::load(config)?;
+    let years = match year {
+        Some(year) => vec![year],
+        None => {
+            let events = stars::parse_events(&web::get_events(cookie)?);
+            let (years, unstarted): (Vec<_>, Vec<_>) =
+                events.into_iter().partition(|(_, stars)| *stars > 0);
+            for (year, _) in unstarted {
+                synced.set_year(year, BTreeMap::new());
+                report.synced.push(report::SyncedYear { year, stars: 0 });
+            }
+            years.into_iter().map(|(year, _)| year).collect()
+        }
+    };
+    for year in years {
+        log::info!("Syncing stars for {}", year);
+        let calendar = web::get_calendar(cookie, year)?;
+        synced.set_year(year, stars::parse_calendar(&calendar));
+        let stars = synced.year(year).values().map(|&n| usize::from(n)).sum();
+        log::info!("{}: {}/50 stars", year, stars);
+        report.synced.push(report::SyncedYear { year, stars });
+    }
+    let written = synced.save(config)?;
+    report.file(config.stars_path(), written);
+    Ok(())
+}
+
+/// Bring all the auto-generated files up to date with the day source files.
+///
+/// Files that haven't changed are not touched. When the build script generates the code, only the
+/// manifest needs updating. Days without an input file get an empty one, like they do from `gen`,
+/// so days whose source file was created some other way are reported as having no input.
+pub fn regenerate(config: &Config, report: &mut Report) -> Result<(), Error> {
+    let project = Project::scan(config)?;
+    for year in project.source_years() {
+        for day in project.source_days(year) {
+            if input::save_placeholder(config, year, day)? {
+                report.file(config.input_path(year, day), true);
+            }
+        }
+    }
+    generate(config, &project, report).map_err(Error::Codegen)
+}
+
+fn generate(config: &Config, project: &Project, report: &mut Report) -> Result {
+    if config.file.build_script {
+        return update_manifest(config, project, report);
+    }
+    auto_files(config, project)?.write(config, report)?;
+    if config.file.layout == Layout::Bins {
+        file_gen::build_lib_rs(config, report)?;
+    }
+    update_manifest(config, project, report)?;
+    Ok(())
+}
+
+/// Generate the files that `cargo-aoc` keeps up to date. Nothing is written until every file has
+/// been generated and checked for changes made by hand.
+fn auto_files(config: &Config, project: &Project) -> Result<AutoFiles> {
+    let mut files = AutoFiles::default();
+    for year in project.source_years() {
+        log::info!("Generating mod file for year {}", year);
+        file_gen::build_mod_file(config, project, year, &mut files)?;
+        if config.file.layout == Layout::Bins {
+            for day in project.source_days(year) {
+                log::info!("Generating binary for year {}, day {}", year, day);
+                file_gen::build_day_bin(config, year, day, &mut files)?;
+            }
+        }
+    }
+    if config.file.layout == Layout::Bins {
+        file_gen::build_runner_rs(config, project, &mut files)?;
+    }
+    log::info!("Generating main.rs");
+    file_gen::build_main_rs(config, project, &mut files)?;
+    Ok(files)
+}
+
+/// Fail if the generated files have been edited by hand, unless running with `--force`.
+///
+/// They are checked again when they are regenerated, but this lets a command fail before it
+/// writes anything else, rather than leave a new day behind.
+fn check_hand_edits(config: &Config) -> Result<(), Error> {
+    if config.force || config.file.build_script {
+        return Ok(());
+    }
+    let project = Project::scan(config)?;
+    auto_files(config, &project)
+        .and_then(|files| files.check(config))
+        .map_err(Error::Codegen)
+}
+
+/// The latest year with puzzles: the current year if we're into december, otherwise the previous
+/// one.
+pub fn current_year() -> u16 {
+    use chrono::Datelike;
+
+    let now = chrono::Utc::now();
+    let year = now.year().try_into().expect("integer conversion");
+    if now.month() == 12 {
+        year
+    } else {
+        year - 1
+    }
+}
+
+/// The year and day of the most recently released puzzle, if it was released today.
+///
+/// Puzzles are released at midnight US Eastern time (UTC-5 in December).
+pub fn todays_puzzle() -> Result<(u16, u8), Error> {
+    use chrono::{Datelike, FixedOffset};
+
+    let now = chrono::Utc::now().with_timezone(&FixedOffset::west(5 * 60 * 60));
+    if now.month() != 12 || now.day() > 25 {
+        return Err(Error::NotReleased(format_err!(
+            "there is no puzzle today ({}), puzzles are released from the 1st to the 25th of \
+            december",
+            now.date().naive_local()
+        )));
+    }
+    Ok((
+        now.year().try_into().expect("integer conversion"),
+        now.day().try_into().expect("integer conversion"),
+    ))
+}
+
+/// Add any dependencies and features the generated code needs to the project's `Cargo.toml`.
+///
+/// Projects created by older versions of `cargo-aoc` may be missing some of them. Only the missing
+/// keys are added, so the rest of the file, comments and all, is left as the user wrote it.
+fn update_manifest(config: &Config, project: &Project, report: &mut Report) -> Result {
+    let cargo_toml_path = config.project_root.join("Cargo.toml");
+    let mut manifest: Document = fs::read_to_string(&cargo_toml_path)?
+        .parse()
+        .with_context(|| format!("cannot parse {}", cargo_toml_path.display()))?;
+    let mut changed = false;
+    for name in ["structopt", "qu", "serde_json"] {
+        changed |= ensure_dep(&mut manifest, "dependencies", name, "*".into())?;
+    }
+    if project.uses_attributes() {
+        let dep = own_dep(codegen::MACROS_VERSION);
+        changed |= ensure_dep(&mut manifest, "dependencies", codegen::MACROS_CRATE, dep)?;
+    }
+    if config.file.build_script {
+        let dep = own_dep(codegen::CODEGEN_VERSION);
+        changed |= ensure_dep(
+            &mut manifest,
+            "build-dependencies",
+            codegen::CODEGEN_CRATE,
+            dep,
+        )?;
+    }
+    for year in project.source_years() {
+        changed |= ensure_feature(&mut manifest, &codegen::year_feature(year))?;
+    }
+    if config.file.layout == Layout::Bins {
+        // There is more than one binary, so `cargo run` needs to know which one to use.
+        if let Some(package) = manifest
+            .get_mut("package")
+            .and_then(Item::as_table_like_mut)
+        {
+            if !package.contains_key("default-run") {
+                let name = package
+                    .get("name")
+                    .cloned()
+                    .unwrap_or_else(|| value(codegen::PROJECT_NAME));
+                package.insert("default-run", name);
+                changed = true;
+            }
+        }
+    }
+    let written = changed && util::write_if_changed(&cargo_toml_path, manifest.to_string())?;
+    report.file(cargo_toml_path, written);
+    Ok(())
+}
+
+/// Add a dependency to the given table, unless it already has one with that name. Returns `true`
+/// if it was added.
+fn ensure_dep(manifest: &mut Document, table: &str, name: &str, dep: Value) -> Result<bool> {
+    let deps = table_mut(manifest, table)?;
+    if deps.contains_key(name) {
+        return Ok(false);
+    }
+    deps.insert(name, value(dep));
+    Ok(true)
+}
+
+/// One of our own crates, pinned to the version the code and config we write are for.
+fn own_dep(version: &str) -> Value {
+    let mut dep = InlineTable::new();
+    dep.insert("version", format!("={}", version).into());
+    dep.insert("git", codegen::REPOSITORY.into());
+    dep.into()
+}
+
+/// Add an empty feature, enabled by default, unless there is already a feature with that name.
+/// Returns `true` if it was added.
+///
+/// An existing feature is left alone, so a year the user took out of the default features stays
+/// out.
+fn ensure_feature(manifest: &mut Document, name: &str) -> Result<bool> {
+    let features = table_mut(manifest, "features")?;
+    if features.contains_key(name) {
+        return Ok(false);
+    }
+    features.insert(name, value(Array::new()));
+    features
+        .entry("default")
+        .or_insert(value(Array::new()))
+        .as_array_mut()
+        .context("`features.default` in Cargo.toml is not an array")?
+        .push(name);
+    Ok(true)
+}
+
+/// The table with the given name, added if the manifest doesn't have it yet.
+fn table_mut<'a>(manifest: &'a mut Document, name: &str) -> Result<&'a mut dyn TableLike> {
+    manifest
+        .entry(name)
+        .or_insert(table())
+        .as_table_like_mut()
+        .with_context(|| format!("`{}` in Cargo.toml is not a table", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_keeps_the_rest_of_the_file() {
+        let original = "[package]\nname = \"aoc\"\n\n\
+            [features]\n# years\ndefault = []\ny2020 = []\n\n\
+            [dependencies]\nqu = \"0.3\" # logging\n";
+        let mut manifest: Document = original.parse().unwrap();
+
+        // Nothing is missing, and a year taken out of the default features stays out.
+        assert!(!ensure_dep(&mut manifest, "dependencies", "qu", "*".into()).unwrap());
+        assert!(!ensure_feature(&mut manifest, "y2020").unwrap());
+        assert_eq!(manifest.to_string(), original);
+
+        assert!(ensure_dep(&mut manifest, "dependencies", "structopt", "*".into()).unwrap());
+        assert!(ensure_feature(&mut manifest, "y2021").unwrap());
+        let dep = own_dep("1.2.3");
+        assert!(ensure_dep(&mut manifest, "build-dependencies", "codegen", dep).unwrap());
+        assert_eq!(
+            manifest.to_string(),
+            "[package]\nname = \"aoc\"\n\n\
+            [features]\n# years\ndefault = [\"y2021\"]\ny2020 = []\ny2021 = []\n\n\
+            [dependencies]\nqu = \"0.3\" # logging\nstructopt = \"*\"\n\n\
+            [build-dependencies]\n\
+            codegen = { version = \"=1.2.3\", git = \"https://github.com/derekdreery/cargo-aoc\" }\n"
+        );
+    }
+}
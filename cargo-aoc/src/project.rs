@@ -0,0 +1,261 @@
+//! What a project has for each year and day, found by looking at its files.
+
+use crate::{puzzle::Puzzle, stars::Stars, util::IoResultExt, Config, Error};
+use cargo_aoc_codegen as codegen;
+use qu::ick_use::*;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
+
+/// What a project has for each year and day.
+///
+/// The tree is scanned once, when the project is loaded. Files only count if their names are
+/// exactly the ones `cargo-aoc` uses, so e.g. `src/_2021/oldday1.rs` and `input/2021/input01.txt`
+/// are left alone.
+#[derive(Debug, Default)]
+pub struct Project {
+    years: BTreeMap<u16, Year>,
+}
+
+/// What a project has for a year.
+#[derive(Debug, Default)]
+pub struct Year {
+    /// Whether there is a folder for the year's source files, `src/_{year}`.
+    pub source_folder: bool,
+    pub days: BTreeMap<u8, Day>,
+}
+
+/// What a project has for a day.
+#[derive(Debug, Default, Clone)]
+pub struct Day {
+    /// Whether there is a source file, `src/_{year}/day{day}.rs`.
+    pub source: bool,
+    /// Whether the source file registers its solutions with `#[aoc::parse]` or `#[aoc::part]`.
+    pub attributes: bool,
+    /// Whether the input has been downloaded.
+    ///
+    /// An empty input file is a placeholder, so it doesn't count.
+    pub input: bool,
+    /// Whether there is an example input, `input/{year}/example{day}.txt`.
+    pub example: bool,
+    /// The answers accepted for the parts solved so far, from the saved puzzle description. Each
+    /// one is a star.
+    pub answers: Vec<String>,
+    /// The stars the website showed for the day at the last `cargo aoc sync`.
+    ///
+    /// These include parts solved without `cargo-aoc`, whose answers we don't know.
+    pub synced_stars: u8,
+}
+
+/// What a project has for a day it has nothing for.
+const NOTHING: &Day = &Day {
+    source: false,
+    attributes: false,
+    input: false,
+    example: false,
+    answers: Vec::new(),
+    synced_stars: 0,
+};
+
+impl Day {
+    /// A star for each part that has been solved, going by both the saved answers and the last
+    /// sync.
+    pub fn stars(&self) -> usize {
+        self.answers.len().max(self.synced_stars.into())
+    }
+}
+
+impl Project {
+    /// Look through the source and input folders of the project, and the stars from the last sync.
+    pub fn scan(config: &Config) -> Result<Self, Error> {
+        fn inner(config: &Config, stars: &Stars) -> Result<Project> {
+            let mut project = Project::default();
+            for year in stars.years() {
+                for (day, stars) in stars.year(year) {
+                    project.day_mut(year, *day).synced_stars = *stars;
+                }
+            }
+            for entry in read_dir(&config.project_root.join("src"))? {
+                let Some(year) = codegen::parse_year(&entry.file_name(), "_") else {
+                    continue;
+                };
+                if !entry.file_type()?.is_dir() {
+                    continue;
+                }
+                project.year_mut(year).source_folder = true;
+                for entry in read_dir(&entry.path())? {
+                    let Some(day) = codegen::parse_day(&entry.file_name(), "day", ".rs") else {
+                        continue;
+                    };
+                    if !entry.file_type()?.is_file() {
+                        continue;
+                    }
+                    let path = entry.path();
+                    let source = fs::read_to_string(&path)
+                        .with_context(|| format!("cannot read {}", path.display()))?;
+                    let info = project.day_mut(year, day);
+                    info.source = true;
+                    info.attributes = codegen::source_uses_attributes(&source);
+                }
+            }
+
+            for entry in read_dir(&config.project_root.join("input"))? {
+                let Some(year) = codegen::parse_year(&entry.file_name(), "") else {
+                    continue;
+                };
+                if !entry.file_type()?.is_dir() {
+                    continue;
+                }
+                for entry in read_dir(&entry.path())? {
+                    let name = entry.file_name();
+                    if let Some(day) = codegen::parse_day(&name, "input", ".txt") {
+                        project.day_mut(year, day).input = entry.metadata()?.len() > 0;
+                    } else if let Some(day) = codegen::parse_day(&name, "example", ".txt") {
+                        project.day_mut(year, day).example = true;
+                    } else if let Some(day) = codegen::parse_day(&name, "puzzle", ".html") {
+                        let path = entry.path();
+                        let html = fs::read_to_string(&path)
+                            .with_context(|| format!("cannot read {}", path.display()))?;
+                        project.day_mut(year, day).answers = Puzzle::parse(&html).answers;
+                    }
+                }
+            }
+            Ok(project)
+        }
+        let stars = Stars::load(config)?;
+        inner(config, &stars).map_err(Error::Io)
+    }
+
+    /// Every year the project has anything for.
+    pub fn years(&self) -> impl Iterator<Item = u16> + '_ {
+        self.years.keys().copied()
+    }
+
+    /// The years with a folder in `src`, which each get a module in the generated code.
+    pub fn source_years(&self) -> impl Iterator<Item = u16> + '_ {
+        self.years
+            .iter()
+            .filter(|(_, year)| year.source_folder)
+            .map(|(year, _)| *year)
+    }
+
+    /// The days of the given year the project has anything for.
+    pub fn days(&self, year: u16) -> impl Iterator<Item = (u8, &Day)> + '_ {
+        self.years
+            .get(&year)
+            .into_iter()
+            .flat_map(|year| year.days.iter().map(|(day, info)| (*day, info)))
+    }
+
+    /// Whether any day registers its solutions with `#[aoc::parse]` or `#[aoc::part]`, so the
+    /// generated code needs the attributes' crate.
+    pub fn uses_attributes(&self) -> bool {
+        self.years
+            .values()
+            .flat_map(|year| year.days.values())
+            .any(|day| day.attributes)
+    }
+
+    /// The days of the given year that have a source file.
+    pub fn source_days(&self, year: u16) -> BTreeSet<u8> {
+        self.days(year)
+            .filter(|(_, day)| day.source)
+            .map(|(day, _)| day)
+            .collect()
+    }
+
+    /// What the project has for the given day.
+    pub fn day(&self, year: u16, day: u8) -> &Day {
+        self.years
+            .get(&year)
+            .and_then(|year| year.days.get(&day))
+            .unwrap_or(NOTHING)
+    }
+
+    /// Update what the project has for the given day, e.g. after downloading its input.
+    pub fn day_mut(&mut self, year: u16, day: u8) -> &mut Day {
+        self.year_mut(year).days.entry(day).or_default()
+    }
+
+    fn year_mut(&mut self, year: u16) -> &mut Year {
+        self.years.entry(year).or_default()
+    }
+
+    /// A calendar of the whole project, with a row for each year and each of input, source and
+    /// the two parts, and a column for each day.
+    pub fn calendar(&self) -> String {
+        type Has = fn(&Day) -> bool;
+        const ROWS: [(&str, &str, Has); 4] = [
+            ("input", "#", |day| day.input),
+            ("source", "#", |day| day.source),
+            ("part 1", "*", |day| day.stars() >= 1),
+            ("part 2", "*", |day| day.stars() >= 2),
+        ];
+
+        let mut calendar = format!("{:8}", "");
+        for day in 1..=25 {
+            calendar.push_str(&format!("{:>3}", day));
+        }
+        calendar.push('\n');
+        for year in self.years() {
+            let stars: usize = self.days(year).map(|(_, day)| day.stars()).sum();
+            calendar.push_str(&format!("{} ({}/50 stars)\n", year, stars));
+            for (label, marker, has) in ROWS {
+                calendar.push_str(&format!("  {:6}", label));
+                for day in 1..=25 {
+                    let marker = if has(self.day(year, day)) {
+                        marker
+                    } else {
+                        "."
+                    };
+                    calendar.push_str(&format!("{:>3}", marker));
+                }
+                calendar.push('\n');
+            }
+        }
+        calendar
+    }
+}
+
+/// The entries of a folder, or none if it doesn't exist.
+fn read_dir(path: &Path) -> Result<Vec<fs::DirEntry>> {
+    let context = || format!("cannot read {}", path.display());
+    match fs::read_dir(path).optional().with_context(context)? {
+        Some(entries) => Ok(entries.collect::<Result<_, _>>().with_context(context)?),
+        None => Ok(vec![]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calendar() {
+        let mut project = Project::default();
+        project.day_mut(2021, 1).input = true;
+        project.day_mut(2021, 2).source = true;
+        let day = project.day_mut(2021, 3);
+        day.input = true;
+        day.source = true;
+        day.answers = vec!["7".into()];
+        // Solved elsewhere, so only the sync knows about it.
+        project.day_mut(2021, 4).synced_stars = 2;
+
+        let dots = "  .".repeat(21);
+        assert_eq!(
+            project.calendar(),
+            format!(
+                "          1  2  3  4  5  6  7  8  9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25\n\
+                2021 (3/50 stars)\n  \
+                input   #  .  #  .{dots}\n  \
+                source  .  #  #  .{dots}\n  \
+                part 1  .  .  *  *{dots}\n  \
+                part 2  .  .  .  *{dots}\n",
+                dots = dots
+            )
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! Pulling information out of puzzle descriptions.
+
+use crate::{util, Config, Error, Report};
+use qu::ick_use::*;
+use regex::Regex;
+use std::fs;
+
+/// The useful parts of a puzzle's web page.
+#[derive(Debug)]
+pub struct Puzzle {
+    /// The title, e.g. "Sonar Sweep".
+    pub title: Option<String>,
+    /// The HTML of the puzzle description (both parts, if part 1 has been solved), and the answers
+    /// to the parts that have been solved.
+    pub description: String,
+    /// The contents of each code block that looks like an example input.
+    pub examples: Vec<String>,
+    /// The answers accepted for the parts that have been solved, in order.
+    pub answers: Vec<String>,
+}
+
+impl Puzzle {
+    /// Parse the HTML of a puzzle page.
+    pub fn parse(html: &str) -> Self {
+        let title = Regex::new(r"<h2[^>]*>--- Day \d+: (.*?) ---</h2>")
+            .unwrap()
+            .captures(html)
+            .map(|caps| unescape(&caps[1]));
+        let description = Regex::new(r"(?s)<article.*?</article>|<p>Your puzzle answer was.*?</p>")
+            .unwrap()
+            .find_iter(html)
+            .map(|part| part.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        // Examples are the multi-line code blocks, single-line ones are usually expected answers.
+        let examples = Regex::new(r"(?s)<pre><code>(.*?)</code></pre>")
+            .unwrap()
+            .captures_iter(&description)
+            .map(|caps| unescape(&strip_tags(&caps[1])))
+            .filter(|example| example.trim_end().contains('\n'))
+            .collect();
+        let answers = Regex::new(r"Your puzzle answer was <code>(.*?)</code>")
+            .unwrap()
+            .captures_iter(html)
+            .map(|caps| unescape(&caps[1]))
+            .collect();
+        Puzzle {
+            title,
+            description,
+            examples,
+            answers,
+        }
+    }
+
+    /// Load the puzzle description saved for the given year and day, if there is one.
+    pub fn load(config: &Config, year: u16, day: u8) -> Result<Option<Self>, Error> {
+        let path = config.puzzle_path(year, day);
+        if !util::path_exists(&path)? {
+            return Ok(None);
+        }
+        Ok(Some(Self::parse(&fs::read_to_string(path)?)))
+    }
+}
+
+/// Save the description, and the first example if there isn't an example file already.
+pub fn save(
+    config: &Config,
+    year: u16,
+    day: u8,
+    puzzle: &Puzzle,
+    report: &mut Report,
+) -> Result<(), Error> {
+    let path = config.puzzle_path(year, day);
+    fs::create_dir_all(path.parent().unwrap())?;
+    fs::write(&path, &puzzle.description)?;
+    report.file(path, true);
+
+    let example_path = config.example_path(year, day);
+    if let Some(example) = puzzle.examples.first() {
+        if util::path_exists(&example_path)? {
+            report.file(example_path, false);
+        } else {
+            log::info!("Saving example for year {}, day {}", year, day);
+            fs::write(&example_path, example)?;
+            report.file(example_path, true);
+        }
+    }
+    Ok(())
+}
+
+fn strip_tags(html: &str) -> String {
+    Regex::new(r"<[^>]*>")
+        .unwrap()
+        .replace_all(html, "")
+        .into_owned()
+}
+
+fn unescape(html: &str) -> String {
+    html.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOLVED: &str = r#"<!DOCTYPE html>
+<html lang="en-us">
+<head><title>Day 1 - Advent of Code 2021</title></head>
+<body>
+<main>
+<article class="day-desc"><h2>--- Day 1: Sonar Sweep ---</h2><p>For example, suppose you had the following report:</p>
+<pre><code>199
+200
+<em>208</em>
+</code></pre>
+<p>In this example, there are <code><em>7</em></code> measurements that are larger than the previous measurement.</p>
+</article>
+<p>Your puzzle answer was <code>1292</code>.</p><article class="day-desc"><h2 id="part2">--- Part Two ---</h2><p>Consider sums of a three-measurement sliding window:</p>
+<pre><code>199  A      607 (N/A - no previous sum)
+200  A B    618 (increased)
+</code></pre>
+</article>
+<p>Your puzzle answer was <code>1262</code>.</p><p class="day-success">Both parts of this puzzle are complete! They provide two gold stars: **</p>
+<p>At this point, you should <a href="/2021">return to your Advent calendar</a>.</p>
+</main>
+</body>
+</html>"#;
+
+    const UNSOLVED: &str = r#"<main>
+<article class="day-desc"><h2>--- Day 5: Supply Stacks &amp; Cranes ---</h2><p>The crates look like this:</p>
+<pre><code>    [D]
+[N] [C]
+ 1   2 &lt;
+</code></pre>
+<p>Then, the rearrangement procedure is <code>move 1 from 2 to 1</code>.</p>
+</article>
+<form method="post" action="5/answer"><input type="text" name="answer"/></form>
+</main>"#;
+
+    #[test]
+    fn solved() {
+        let puzzle = Puzzle::parse(SOLVED);
+        assert_eq!(puzzle.title.as_deref(), Some("Sonar Sweep"));
+        assert_eq!(puzzle.answers, ["1292", "1262"]);
+        assert_eq!(
+            puzzle.examples,
+            [
+                "199\n200\n208\n",
+                "199  A      607 (N/A - no previous sum)\n200  A B    618 (increased)\n",
+            ]
+        );
+        // Both parts and their answers, and nothing else from the page.
+        assert!(puzzle
+            .description
+            .starts_with("<article class=\"day-desc\"><h2>--- Day 1"));
+        assert!(puzzle.description.contains("--- Part Two ---"));
+        assert!(puzzle
+            .description
+            .ends_with("<p>Your puzzle answer was <code>1262</code>.</p>"));
+        assert!(!puzzle.description.contains("day-success"));
+        assert_eq!(Puzzle::parse(&puzzle.description).answers, puzzle.answers);
+    }
+
+    #[test]
+    fn unsolved() {
+        let puzzle = Puzzle::parse(UNSOLVED);
+        assert_eq!(puzzle.title.as_deref(), Some("Supply Stacks & Cranes"));
+        assert!(puzzle.answers.is_empty());
+        assert_eq!(puzzle.examples, ["    [D]\n[N] [C]\n 1   2 <\n"]);
+        assert!(!puzzle.description.contains("<form"));
+    }
+
+    #[test]
+    fn not_a_puzzle() {
+        let puzzle = Puzzle::parse("<html><body>Please log in.</body></html>");
+        assert_eq!(puzzle.title, None);
+        assert_eq!(puzzle.description, "");
+        assert!(puzzle.examples.is_empty());
+        assert!(puzzle.answers.is_empty());
+    }
+}
@@ -0,0 +1,111 @@
+//! What a command did, for tools that read `cargo-aoc`'s output rather than its log.
+
+use crate::Error;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// What a command did, printed as JSON with `--json`.
+///
+/// The lists are always there, even when they are empty. The other fields are only there for the
+/// commands they apply to.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    /// The days whose input was downloaded.
+    pub downloaded: Vec<Day>,
+    /// The files that were written.
+    pub generated: Vec<PathBuf>,
+    /// The files that were left alone, because they already existed or were already up to date.
+    pub skipped: Vec<PathBuf>,
+    /// What stopped the command, if it failed.
+    pub errors: Vec<ErrorReport>,
+    /// The root of the project, for `new`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<PathBuf>,
+    /// The stored session cookie, for `show-cookie`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cookie: Option<String>,
+    /// The day the command was for, for `gen` and `today`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub day: Option<Day>,
+    /// The title of the puzzle, for `today`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The source file for the day, for `gen` and `today`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<PathBuf>,
+    /// The URL of the puzzle, for `today`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// What the project has for each day it has anything for, for `status`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<DayStatus>,
+    /// The years whose stars were fetched from the website, for `sync`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub synced: Vec<SyncedYear>,
+}
+
+/// A year and day.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub struct Day {
+    pub year: u16,
+    pub day: u8,
+}
+
+/// What the project has for a day, as it appears in a [`Report`].
+#[derive(Debug, Serialize)]
+pub struct DayStatus {
+    pub year: u16,
+    pub day: u8,
+    /// Whether the input has been downloaded.
+    pub input: bool,
+    /// Whether there is a source file.
+    pub source: bool,
+    /// Whether there is an example input.
+    pub example: bool,
+    /// How many parts have been solved.
+    pub stars: usize,
+}
+
+/// A year whose stars were fetched from the website, as it appears in a [`Report`].
+#[derive(Debug, Serialize)]
+pub struct SyncedYear {
+    pub year: u16,
+    /// The stars the website shows for the whole year.
+    pub stars: usize,
+}
+
+/// An error, as it appears in a [`Report`].
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    /// What failed, e.g. `auth`, see [`Error::kind`].
+    pub kind: &'static str,
+    /// The code `cargo-aoc` exits with.
+    pub exit_code: i32,
+    /// The error, with its causes, on one line.
+    pub message: String,
+}
+
+impl Report {
+    /// Record a file that was written if `written`, or left alone otherwise.
+    pub fn file(&mut self, path: impl Into<PathBuf>, written: bool) {
+        if written {
+            self.generated.push(path.into());
+        } else {
+            self.skipped.push(path.into());
+        }
+    }
+}
+
+impl ErrorReport {
+    /// Describe an error, using the first [`Error`] in its chain to say what failed.
+    ///
+    /// Errors that don't say what failed are reported as `other`, with exit code 1.
+    pub fn new(e: &anyhow::Error) -> Self {
+        let kind = e.chain().find_map(|e| e.downcast_ref::<Error>());
+        ErrorReport {
+            kind: kind.map_or("other", Error::kind),
+            exit_code: kind.map_or(1, Error::exit_code),
+            message: format!("{:#}", e),
+        }
+    }
+}
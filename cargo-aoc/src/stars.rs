@@ -0,0 +1,217 @@
+//! The stars the website shows for each day, which `cargo aoc sync` keeps in `.aoc/stars.toml`.
+//!
+//! These count parts solved anywhere, not just the ones whose answers are in a saved puzzle
+//! description.
+
+use crate::{
+    util::{self, IoResultExt},
+    Config, Error,
+};
+use qu::ick_use::*;
+use regex::Regex;
+use std::{collections::BTreeMap, fs};
+
+/// The stars for each year and day, as of the last sync.
+///
+/// Stored as a table for each year, mapping days to their stars, e.g. `[2021]` then `1 = 2`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Stars {
+    years: BTreeMap<u16, BTreeMap<u8, u8>>,
+}
+
+impl Stars {
+    /// Load the stars from the last sync, or nothing if the project has never been synced.
+    pub fn load(config: &Config) -> Result<Self, Error> {
+        let path = config.stars_path();
+        let Some(text) = fs::read_to_string(&path).optional()? else {
+            return Ok(Self::default());
+        };
+        Self::from_toml(&text)
+            .with_context(|| format!("cannot read stars from {}", path.display()))
+            .map_err(Error::Config)
+    }
+
+    /// Save the stars, unless the file already has them. Returns `true` if the file was written.
+    pub fn save(&self, config: &Config) -> Result<bool, Error> {
+        let path = config.stars_path();
+        fs::create_dir_all(path.parent().unwrap())?;
+        Ok(util::write_if_changed(&path, self.to_toml())?)
+    }
+
+    /// The stars for the given year and day.
+    pub fn get(&self, year: u16, day: u8) -> u8 {
+        self.year(year).get(&day).copied().unwrap_or(0)
+    }
+
+    /// The stars for each day of the given year that has any.
+    pub fn year(&self, year: u16) -> &BTreeMap<u8, u8> {
+        const NONE: &BTreeMap<u8, u8> = &BTreeMap::new();
+        self.years.get(&year).unwrap_or(NONE)
+    }
+
+    /// Every year with stars.
+    pub fn years(&self) -> impl Iterator<Item = u16> + '_ {
+        self.years.keys().copied()
+    }
+
+    /// Replace the stars for the given year, e.g. with ones from [`parse_calendar`].
+    pub fn set_year(&mut self, year: u16, mut days: BTreeMap<u8, u8>) {
+        days.retain(|_, stars| *stars > 0);
+        if days.is_empty() {
+            self.years.remove(&year);
+        } else {
+            self.years.insert(year, days);
+        }
+    }
+
+    // TOML keys are always strings, so the numbers are converted by hand.
+
+    fn from_toml(text: &str) -> Result<Self> {
+        let file: BTreeMap<String, BTreeMap<String, u8>> = toml::from_str(text)?;
+        let mut stars = Self::default();
+        for (year, days) in file {
+            let year = year
+                .parse()
+                .with_context(|| format!("{:?} is not a year", year))?;
+            let days = days
+                .into_iter()
+                .map(|(day, stars)| match day.parse() {
+                    Ok(day) if (1..=25).contains(&day) && stars <= 2 => Ok((day, stars)),
+                    _ => Err(format_err!(
+                        "{:?} = {} is not a day and its stars",
+                        day,
+                        stars
+                    )),
+                })
+                .collect::<Result<_>>()?;
+            stars.set_year(year, days);
+        }
+        Ok(stars)
+    }
+
+    fn to_toml(&self) -> String {
+        let mut text = String::new();
+        for (year, days) in &self.years {
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            text.push_str(&format!("[{}]\n", year));
+            for (day, stars) in days {
+                text.push_str(&format!("{} = {}\n", day, stars));
+            }
+        }
+        text
+    }
+}
+
+/// Get the stars for each day from the HTML of a year's calendar page, e.g.
+/// `https://adventofcode.com/2021`.
+///
+/// Days with one star have the class `calendar-complete`, days with two `calendar-verycomplete`.
+pub fn parse_calendar(html: &str) -> BTreeMap<u8, u8> {
+    Regex::new(r#"class="calendar-day(\d+)([^"]*)""#)
+        .unwrap()
+        .captures_iter(html)
+        .filter_map(|caps| {
+            let day = caps[1].parse().ok()?;
+            let classes = caps[2].split_whitespace().collect::<Vec<_>>();
+            let stars = if classes.contains(&"calendar-verycomplete") {
+                2
+            } else if classes.contains(&"calendar-complete") {
+                1
+            } else {
+                0
+            };
+            Some((day, stars))
+        })
+        .collect()
+}
+
+/// Get every year and its total stars from the HTML of the events page,
+/// `https://adventofcode.com/events`.
+///
+/// Years without any stars are included, with none.
+pub fn parse_events(html: &str) -> BTreeMap<u16, u32> {
+    Regex::new(r#"<a href="/(\d+)">\[\d+\]</a>(?:\s*<span class="star-count">(\d+)\*</span>)?"#)
+        .unwrap()
+        .captures_iter(html)
+        .filter_map(|caps| {
+            let year = caps[1].parse().ok()?;
+            let stars = caps
+                .get(2)
+                .map_or(Some(0), |stars| stars.as_str().parse().ok())?;
+            Some((year, stars))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CALENDAR: &str = r#"<pre class="calendar calendar-perfect">
+<a aria-label="Day 1, two stars" href="/2021/day/1" class="calendar-day1 calendar-verycomplete">  <span class="calendar-day"> 1</span> <span class="calendar-mark-complete">*</span><span class="calendar-mark-verycomplete">*</span></a>
+<a aria-label="Day 2, one star" href="/2021/day/2" class="calendar-day2 calendar-complete">  <span class="calendar-day"> 2</span> <span class="calendar-mark-complete">*</span><span class="calendar-mark-verycomplete">*</span></a>
+<a aria-label="Day 3" href="/2021/day/3" class="calendar-day3">  <span class="calendar-day"> 3</span> <span class="calendar-mark-complete">*</span><span class="calendar-mark-verycomplete">*</span></a>
+<span aria-hidden="true" class="calendar-day4">                     <span class="calendar-day"> 4</span></span>
+</pre>"#;
+
+    const EVENTS: &str = r#"<main>
+<article><p>Here are the events you can still play:</p>
+<div class="eventlist-event"><a href="/2022">[2022]</a> <span class="star-count">12*</span></div>
+<div class="eventlist-event"><a href="/2021">[2021]</a> <span class="star-count">50*</span></div>
+<div class="eventlist-event"><a href="/2020">[2020]</a></div>
+<p>Total stars: <span class="star-count">62*</span></p>
+</article>
+</main>"#;
+
+    #[test]
+    fn calendar() {
+        assert_eq!(
+            parse_calendar(CALENDAR),
+            BTreeMap::from([(1, 2), (2, 1), (3, 0), (4, 0)])
+        );
+    }
+
+    #[test]
+    fn calendar_without_days() {
+        assert!(parse_calendar("<pre class=\"calendar\"></pre>").is_empty());
+    }
+
+    #[test]
+    fn events() {
+        assert_eq!(
+            parse_events(EVENTS),
+            BTreeMap::from([(2020, 0), (2021, 50), (2022, 12)])
+        );
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let mut stars = Stars::default();
+        stars.set_year(2021, parse_calendar(CALENDAR));
+        stars.set_year(2020, BTreeMap::from([(25, 2)]));
+        stars.set_year(2019, BTreeMap::from([(1, 0)]));
+
+        let text = stars.to_toml();
+        assert_eq!(text, "[2020]\n25 = 2\n\n[2021]\n1 = 2\n2 = 1\n");
+        let read = Stars::from_toml(&text).unwrap();
+        assert_eq!(read, stars);
+        assert_eq!(read.get(2021, 1), 2);
+        assert_eq!(read.get(2021, 3), 0);
+        assert_eq!(read.get(2019, 1), 0);
+        assert_eq!(read.years().collect::<Vec<_>>(), [2020, 2021]);
+    }
+
+    #[test]
+    fn bad_toml() {
+        for text in [
+            "[2021]\n26 = 1\n",
+            "[2021]\n1 = 3\n",
+            "[y2021]\n1 = 1\n",
+            "[2021]\nx = 1\n",
+        ] {
+            assert!(Stars::from_toml(text).is_err(), "{}", text);
+        }
+    }
+}
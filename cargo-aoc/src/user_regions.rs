@@ -0,0 +1,271 @@
+//! Parts of generated files that belong to the user.
+//!
+//! A user region looks like
+//!
+//! ```text
+//! // aoc:user-begin imports
+//! use std::collections::HashMap;
+//! // aoc:user-end
+//! ```
+//!
+//! Whatever is between the markers is read back from the old file, and put in the same region
+//! when the file is regenerated.
+
+use proc_macro2::TokenStream;
+use qu::ick_use::*;
+use quote::{format_ident, quote};
+use regex::{Captures, Regex};
+use std::{collections::BTreeMap, path::Path};
+
+const BEGIN: &str = "// aoc:user-begin";
+const END: &str = "// aoc:user-end";
+
+/// A user region where an item or statement could go.
+pub fn placeholder(name: &str) -> TokenStream {
+    let name = format_ident!("{}", name);
+    quote!(__aoc_user_region!(#name);)
+}
+
+/// A user region in the fields of a struct.
+pub fn field_placeholder(name: &str) -> TokenStream {
+    let field = format_ident!("__aoc_user_region_{}", name);
+    quote!(#field: (),)
+}
+
+/// Read the contents of the user regions in the existing contents of the file at `path`, by name.
+pub fn read(source: &str, path: &Path) -> Result<BTreeMap<String, String>> {
+    let mut regions = BTreeMap::new();
+    let mut current: Option<(String, String)> = None;
+    for (idx, line) in source.lines().enumerate() {
+        let trimmed = line.trim();
+        match (&mut current, trimmed.strip_prefix(BEGIN)) {
+            (None, Some(name)) => current = Some((name.trim().to_string(), String::new())),
+            (Some((name, _)), Some(_)) => bail!(
+                "user region `{}` in {} is not closed before line {}",
+                name,
+                path.display(),
+                idx + 1
+            ),
+            (Some(_), None) if trimmed == END => {
+                let (name, content) = current.take().unwrap();
+                regions.insert(name, content);
+            }
+            (Some((_, content)), None) => {
+                content.push_str(line);
+                content.push('\n');
+            }
+            (None, None) => (),
+        }
+    }
+    if let Some((name, _)) = current {
+        bail!(
+            "user region `{}` in {} is missing `{}`",
+            name,
+            path.display(),
+            END
+        );
+    }
+    Ok(regions)
+}
+
+/// Remove the contents of the user regions, leaving the markers.
+pub fn strip(source: &str) -> String {
+    let mut stripped = String::new();
+    let mut in_region = false;
+    for line in source.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with(BEGIN) {
+            in_region = true;
+        } else if trimmed == END {
+            in_region = false;
+        } else if in_region {
+            continue;
+        }
+        stripped.push_str(line);
+        stripped.push('\n');
+    }
+    stripped
+}
+
+/// Replace the placeholders in generated code with user regions, filled from `regions`.
+///
+/// The markers always get lines of their own, so they don't comment out any code if the generated
+/// code couldn't be formatted.
+///
+/// Fails rather than lose the contents of a region that is no longer generated.
+pub fn fill(code: &str, mut regions: BTreeMap<String, String>, path: &Path) -> Result<String> {
+    let regex = Regex::new(
+        r"(?m)(^[ \t]*)?(?:__aoc_user_region\s*!\s*\(\s*(\w+)\s*\)\s*;|__aoc_user_region_(\w+)\s*:\s*\(\s*\)\s*,)(\n)?",
+    )
+    .unwrap();
+    let filled = regex.replace_all(code, |caps: &Captures| {
+        let name = caps.get(2).or_else(|| caps.get(3)).unwrap().as_str();
+        let content = regions.remove(name).unwrap_or_default();
+        let (start, indent) = match caps.get(1) {
+            Some(indent) => ("", indent.as_str()),
+            None => ("\n", ""),
+        };
+        format!(
+            "{}{}{} {}\n{}{}{}\n",
+            start, indent, BEGIN, name, content, indent, END
+        )
+    });
+    if let Some(name) = regions.keys().next() {
+        bail!(
+            "user region `{}` in {} is no longer generated, move its contents elsewhere and \
+            remove its markers",
+            name,
+            path.display()
+        );
+    }
+    Ok(filled.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cargo_aoc_codegen as codegen;
+
+    const PATH: &str = "src/main.rs";
+
+    /// Generated code with a region for items, one for statements, and one for struct fields.
+    fn generated(extra: TokenStream) -> String {
+        let items = placeholder("items");
+        let main = placeholder("main");
+        let fields = field_placeholder("opt");
+        codegen::format(quote!(
+            #items
+
+            pub struct Opt {
+                pub year: u16,
+                #fields
+            }
+
+            fn main() {
+                #main
+                #extra
+            }
+        ))
+        .unwrap()
+    }
+
+    fn fill(code: &str, regions: BTreeMap<String, String>) -> Result<String> {
+        super::fill(code, regions, Path::new(PATH))
+    }
+
+    fn read(source: &str) -> Result<BTreeMap<String, String>> {
+        super::read(source, Path::new(PATH))
+    }
+
+    #[test]
+    fn empty_regions() {
+        let filled = fill(&generated(quote!()), BTreeMap::new()).unwrap();
+        assert_eq!(
+            filled,
+            "// aoc:user-begin items\n\
+            // aoc:user-end\n\
+            \n\
+            pub struct Opt {\n    \
+                pub year: u16,\n    \
+                // aoc:user-begin opt\n    \
+                // aoc:user-end\n\
+            }\n\
+            \n\
+            fn main() {\n    \
+                // aoc:user-begin main\n    \
+                // aoc:user-end\n\
+            }\n"
+        );
+        assert!(read(&filled).unwrap().values().all(String::is_empty));
+    }
+
+    #[test]
+    fn round_trip() {
+        let old = fill(&generated(quote!()), BTreeMap::new())
+            .unwrap()
+            .replace(
+                "// aoc:user-begin items\n",
+                "// aoc:user-begin items\nuse std::collections::HashMap;\n",
+            )
+            .replace(
+                "    // aoc:user-begin opt\n",
+                "    // aoc:user-begin opt\n    /// Verbose.\n    #[structopt(long)]\n    pub verbose: bool,\n",
+            )
+            .replace(
+                "    // aoc:user-begin main\n",
+                "    // aoc:user-begin main\n    if true {\n        println!(\"hi\");\n    }\n",
+            );
+        let regions = read(&old).unwrap();
+        assert_eq!(regions["items"], "use std::collections::HashMap;\n");
+        assert_eq!(
+            regions["opt"],
+            "    /// Verbose.\n    #[structopt(long)]\n    pub verbose: bool,\n"
+        );
+        assert_eq!(
+            regions["main"],
+            "    if true {\n        println!(\"hi\");\n    }\n"
+        );
+
+        // The same code, filled with the regions of the old file, is the old file.
+        assert_eq!(fill(&generated(quote!()), regions.clone()).unwrap(), old);
+
+        // New generated code keeps the regions, indentation and all.
+        let new = fill(&generated(quote!(run();)), regions.clone()).unwrap();
+        assert_eq!(read(&new).unwrap(), regions);
+        assert!(new.contains("    // aoc:user-end\n    run();\n}"));
+    }
+
+    #[test]
+    fn region_no_longer_generated() {
+        let regions = BTreeMap::from([
+            ("main".to_string(), String::new()),
+            ("gone".to_string(), "fn helper() {}\n".to_string()),
+        ]);
+        let error = fill(&generated(quote!()), regions).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "user region `gone` in src/main.rs is no longer generated, move its contents \
+            elsewhere and remove its markers"
+        );
+    }
+
+    #[test]
+    fn unclosed_region() {
+        let error = read("// aoc:user-begin items\nuse std::fmt;\n")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "user region `items` in src/main.rs is missing `// aoc:user-end`"
+        );
+
+        let error = read("// aoc:user-begin items\n// aoc:user-begin main\n// aoc:user-end\n")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "user region `items` in src/main.rs is not closed before line 2"
+        );
+    }
+
+    #[test]
+    fn placeholder_after_code() {
+        // Code that couldn't be formatted has everything on one line, so the markers need lines
+        // of their own.
+        let code = format!("fn main() {{ {} }}", placeholder("main"));
+        let regions = BTreeMap::from([("main".to_string(), "run();\n".to_string())]);
+        assert_eq!(
+            fill(&code, regions).unwrap(),
+            "fn main() { \n// aoc:user-begin main\nrun();\n// aoc:user-end\n }"
+        );
+    }
+
+    #[test]
+    fn strip_keeps_markers() {
+        let source = "a\n    // aoc:user-begin main\n    b\n    // aoc:user-end\nc\n";
+        assert_eq!(
+            strip(source),
+            "a\n    // aoc:user-begin main\n    // aoc:user-end\nc\n"
+        );
+    }
+}
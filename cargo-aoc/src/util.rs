@@ -16,6 +16,70 @@ impl<T> IoResultExt<T> for io::Result<T> {
     }
 }
 
+/// Write `contents` to `path`, unless the file already contains exactly that.
+///
+/// This avoids touching files that haven't changed, which would trigger a rebuild. Returns `true`
+/// if the file was written.
+pub fn write_if_changed(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<bool> {
+    let (path, contents) = (path.as_ref(), contents.as_ref());
+    if fs::read(path).optional()?.as_deref() == Some(contents) {
+        return Ok(false);
+    }
+    fs::write(path, contents)?;
+    Ok(true)
+}
+
 pub fn path_exists(path: impl AsRef<Path>) -> io::Result<bool> {
     Ok(fs::metadata(path).optional()?.is_some())
 }
+
+/// The lines of `old` that are not in `new`, with their line numbers, counting from 1.
+///
+/// Found with a line-by-line diff, so lines that only moved around are left out.
+pub fn removed_lines<'a>(old: &'a str, new: &str) -> Vec<(usize, &'a str)> {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+    // lengths[i][j] is the length of the longest common subsequence of old[i..] and new[j..].
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut removed = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() {
+        if j < new.len() && old[i] == new[j] {
+            j += 1;
+        } else if j == new.len() || lengths[i + 1][j] >= lengths[i][j + 1] {
+            removed.push((i + 1, old[i]));
+        } else {
+            j += 1;
+            continue;
+        }
+        i += 1;
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removed() {
+        let old = "a\nb\nc\nd\ne\n";
+        assert_eq!(removed_lines(old, old), []);
+        assert_eq!(removed_lines(old, "a\nc\nx\ne\ny\n"), [(2, "b"), (4, "d")]);
+        assert_eq!(
+            removed_lines(old, ""),
+            [(1, "a"), (2, "b"), (3, "c"), (4, "d"), (5, "e")]
+        );
+        assert_eq!(removed_lines("", old), []);
+    }
+}
@@ -0,0 +1,114 @@
+//! Watching a day's files for changes.
+
+use crate::{util::IoResultExt, Config, Error};
+use cargo_aoc_codegen as codegen;
+use qu::ick_use::*;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// How often to check the watched files for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Polls the files that affect the result of running a day, and reports when any of them change.
+///
+/// These are the day's source, input and example input, and every other source file the user
+/// wrote. Files generated by `cargo-aoc` are left out, since they are regenerated from the others.
+///
+/// The files are listed again each time, so e.g. a new module is watched as soon as it is created.
+/// The day's files are watched even if they don't exist yet, so e.g. creating an example file
+/// triggers a change.
+pub struct Watcher {
+    src: PathBuf,
+    day_files: Vec<PathBuf>,
+    /// Files that are regenerated before each run.
+    generated: Vec<PathBuf>,
+    modified: BTreeMap<PathBuf, Option<SystemTime>>,
+}
+
+impl Watcher {
+    pub fn new(config: &Config, year: u16, day: u8) -> Self {
+        let src = config.project_root.join("src");
+        let mut this = Self {
+            day_files: vec![
+                config.day_source(year, day),
+                config.input_path(year, day),
+                config.example_path(year, day),
+            ],
+            generated: vec![src.join("main.rs"), src.join("runner.rs"), src.join("bin")],
+            src,
+            modified: BTreeMap::new(),
+        };
+        // If a file can't be read we will find out when we next poll.
+        this.modified = this.modified().unwrap_or_default();
+        this
+    }
+
+    /// Block until one of the files is created, modified or deleted.
+    pub fn wait_for_change(&mut self) -> Result<(), Error> {
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let modified = self.modified()?;
+            if modified != self.modified {
+                self.modified = modified;
+                return Ok(());
+            }
+        }
+    }
+
+    fn modified(&self) -> Result<BTreeMap<PathBuf, Option<SystemTime>>, Error> {
+        let mut paths = self.day_files.clone();
+        self.source_files(&self.src, &mut paths)
+            .with_context(|| format!("cannot watch {}", self.src.display()))
+            .map_err(Error::Io)?;
+        paths
+            .into_iter()
+            .map(|path| {
+                let modified = fs::metadata(&path)
+                    .and_then(|meta| meta.modified())
+                    .optional()
+                    .with_context(|| format!("cannot watch {}", path.display()))
+                    .map_err(Error::Io)?;
+                Ok((path, modified))
+            })
+            .collect()
+    }
+
+    /// Add the source files in `dir` and its subfolders, apart from generated ones.
+    fn source_files(&self, dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if self.is_generated(&path) {
+                continue;
+            }
+            if path.is_dir() {
+                self.source_files(&path, paths)?;
+            } else if path.extension().is_some_and(|ext| ext == "rs") {
+                paths.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    fn is_generated(&self, path: &Path) -> bool {
+        // Each year's `mod.rs` is generated too.
+        let is_year_mod = path.file_name().is_some_and(|name| name == "mod.rs")
+            && path
+                .parent()
+                .and_then(Path::file_name)
+                .and_then(|name| codegen::parse_year(name, "_"))
+                .is_some();
+        is_year_mod || self.generated.iter().any(|generated| path == generated)
+    }
+}
+
+/// Clear the terminal, so each run replaces the output of the last one.
+pub fn clear_screen() {
+    print!("\x1b[2J\x1b[H");
+    let _ = io::stdout().flush();
+}
@@ -1,21 +1,80 @@
+//! Talking to the Advent of Code website.
+
+use crate::Error;
 use qu::ick_use::*;
-use reqwest::blocking::Client;
+use reqwest::{blocking::Client, StatusCode};
 
 thread_local! {
     pub static CLIENT: Client = Client::new();
 }
 
-pub fn get_day(cookie: &str, year: u16, day: u8) -> Result<String> {
-    let url = format!("https://adventofcode.com/{}/day/{}/input", year, day);
+/// Get the puzzle input for the given day.
+pub fn get_day(cookie: &str, year: u16, day: u8) -> Result<String, Error> {
+    get(cookie, format!("{}/input", puzzle_url(year, day)))
+}
+
+/// Get the HTML page with the puzzle description.
+///
+/// Part 2 is only included once part 1 has been solved.
+pub fn get_puzzle(cookie: &str, year: u16, day: u8) -> Result<String, Error> {
+    get(cookie, puzzle_url(year, day))
+}
+
+/// Get the HTML page with the calendar for the given year, which shows the stars for each day.
+pub fn get_calendar(cookie: &str, year: u16) -> Result<String, Error> {
+    get_logged_in(cookie, format!("https://adventofcode.com/{}", year))
+}
+
+/// Get the HTML page listing every year, with the total stars for each.
+pub fn get_events(cookie: &str) -> Result<String, Error> {
+    get_logged_in(cookie, "https://adventofcode.com/events".into())
+}
+
+/// The URL of the puzzle description.
+pub fn puzzle_url(year: u16, day: u8) -> String {
+    format!("https://adventofcode.com/{}/day/{}", year, day)
+}
+
+/// Get a page that is also shown to visitors who aren't logged in.
+///
+/// The website shows these pages without any stars rather than refusing the cookie, so check that
+/// we are logged in (the page shows who as).
+fn get_logged_in(cookie: &str, url: String) -> Result<String, Error> {
+    let page = get(cookie, url)?;
+    if !page.contains(r#"<div class="user">"#) {
+        return Err(Error::Auth(format_err!(COOKIE_REJECTED)));
+    }
+    Ok(page)
+}
+
+/// Get a page, sorting the ways it can fail by what the website's reply means.
+fn get(cookie: &str, url: String) -> Result<String, Error> {
     log::info!("fetching {}", url);
 
     let res = CLIENT
         .with(|client| client.get(url))
         .header("Cookie", format!("session={}", cookie))
-        .send()?;
-    let res = res.error_for_status()?;
-    let res = res.text()?;
-    Ok(res)
+        .send()
+        .map_err(|e| Error::Network(e.into()))?;
+    let status = res.status();
+    let res = res.error_for_status().map_err(|e| {
+        let e = anyhow::Error::from(e);
+        match status {
+            // The website asks us to log in when it doesn't recognise the cookie.
+            StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Error::Auth(e.context(COOKIE_REJECTED))
+            }
+            StatusCode::NOT_FOUND => Error::NotReleased(e.context("the puzzle is still locked")),
+            StatusCode::TOO_MANY_REQUESTS => Error::RateLimit(e.context(
+                "the website is limiting how often we can make requests, try again later",
+            )),
+            _ => Error::Network(e),
+        }
+    })?;
+    res.text().map_err(|e| Error::Network(e.into()))
 }
 
+const COOKIE_REJECTED: &str = "the website did not accept your cookie, it may have expired (get a \
+    new one and store it with `cargo aoc set-cookie`)";
+
 //Cookie: session=.....